@@ -0,0 +1,196 @@
+use std::path::Path;
+
+use anyhow::{anyhow, Context, Result};
+use bech32::{FromBase32, ToBase32, Variant};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{ext::TakeExt, find_artifacts_dir, hashing::file_sha256_digest, optimized_artifacts};
+
+/// Address type of module accounts, which contract addresses are derived as.
+const MODULE: &str = "module";
+/// Prefix of the instantiate2 address derivation key.
+const WASM_PREFIX: &[u8] = b"wasm\0";
+const MAX_SALT_LENGTH: usize = 64;
+
+/// Contract addresses predicted for an instantiate2 deployment of the artifacts.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AddressPrediction {
+    /// The bech32 address of the instantiating account.
+    pub creator: String,
+    /// Hex encoded salt.
+    pub salt: String,
+    /// The instantiate msg the addresses were derived with, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub msg: Option<String>,
+    /// Entries sorted by artifact name.
+    pub contracts: Vec<PredictedAddress>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PredictedAddress {
+    /// Filename of the optimized artifact.
+    pub artifact: String,
+    /// Hex encoded SHA-256 checksum of the artifact, i.e. its on-chain code hash.
+    pub checksum: String,
+    pub address: String,
+}
+
+/// Predicts the instantiate2 address of each optimized artifact in the artifacts dir, when instantiated
+/// by the creator with the salt, and prints them either as a table or as JSON.
+/// The salt is taken as UTF-8, or decoded from hex if `hex_salt` is set. Like in `instantiate2`, the msg is
+/// only part of the derivation if it's given, i.e. with `fix_msg` set.
+pub fn predict_addresses<P: AsRef<Path>>(
+    workspace_path: P,
+    creator: &str,
+    salt: &str,
+    hex_salt: bool,
+    msg: Option<&str>,
+    json: bool,
+) -> Result<()> {
+    let artifacts_dir = find_artifacts_dir(workspace_path)?;
+    let salt = match hex_salt {
+        true => hex::decode(salt).context(format!("Invalid hex salt: {}", salt))?,
+        false => salt.as_bytes().to_vec(),
+    };
+    let (prefix, canonical_creator) = decode_address(creator)?;
+
+    let wasm_paths = optimized_artifacts(&artifacts_dir)?;
+
+    let contracts = wasm_paths
+        .iter()
+        .map(|wasm_path| {
+            let checksum = file_sha256_digest(wasm_path)?;
+            let address = instantiate2_address(
+                &hex::decode(&checksum)?,
+                &canonical_creator,
+                &salt,
+                msg.unwrap_or_default().as_bytes(),
+            )?;
+            Ok(PredictedAddress {
+                artifact: wasm_path.rtake(1).display().to_string(),
+                checksum,
+                address: encode_address(&prefix, &address)?,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let prediction = AddressPrediction {
+        creator: creator.to_string(),
+        salt: hex::encode(&salt),
+        msg: msg.map(String::from),
+        contracts,
+    };
+    match json {
+        true => println!("{}", serde_json::to_string_pretty(&prediction)?),
+        false => print_prediction(&prediction),
+    }
+
+    Ok(())
+}
+
+/// Derives the canonical address of a contract instantiated with instantiate2, like `wasmd` does.
+pub fn instantiate2_address(
+    checksum: &[u8],
+    creator: &[u8],
+    salt: &[u8],
+    msg: &[u8],
+) -> Result<Vec<u8>> {
+    if checksum.len() != 32 {
+        return Err(anyhow!(
+            "Invalid checksum length: {} bytes, expected 32",
+            checksum.len()
+        ));
+    }
+    if salt.is_empty() || salt.len() > MAX_SALT_LENGTH {
+        return Err(anyhow!(
+            "Invalid salt length: {} bytes, expected 1 to {}",
+            salt.len(),
+            MAX_SALT_LENGTH
+        ));
+    }
+
+    let mut key = WASM_PREFIX.to_vec();
+    for component in [checksum, creator, salt, msg] {
+        key.extend_from_slice(&(component.len() as u64).to_be_bytes());
+        key.extend_from_slice(component);
+    }
+
+    // the module account address derivation of the Cosmos SDK (ADR-028)
+    Ok(Sha256::new()
+        .chain_update(Sha256::digest(MODULE))
+        .chain_update(key)
+        .finalize()
+        .to_vec())
+}
+
+/// Decodes a bech32 address into its prefix & canonical address.
+pub fn decode_address(address: &str) -> Result<(String, Vec<u8>)> {
+    let (prefix, data, variant) =
+        bech32::decode(address).context(format!("Invalid bech32 address: {}", address))?;
+    if variant != Variant::Bech32 {
+        return Err(anyhow!("Invalid bech32 address: {} uses bech32m", address));
+    }
+
+    Ok((prefix, Vec::<u8>::from_base32(&data)?))
+}
+
+/// Encodes a canonical address as a bech32 address with the prefix.
+pub fn encode_address(prefix: &str, canonical: &[u8]) -> Result<String> {
+    Ok(bech32::encode(
+        prefix,
+        canonical.to_base32(),
+        Variant::Bech32,
+    )?)
+}
+
+fn print_prediction(prediction: &AddressPrediction) {
+    println!(
+        "📍  Predicted addresses for {} with salt {}:",
+        prediction.creator, prediction.salt
+    );
+    for contract in &prediction.contracts {
+        println!(
+            "    ...{:<40} {}  {}",
+            contract.artifact,
+            &contract.checksum[..12],
+            contract.address
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::address::{decode_address, encode_address, instantiate2_address};
+
+    #[test]
+    fn derives_instantiate2_addresses() {
+        let checksum =
+            hex::decode("13a1fc994cc6d1c81b746ee0c0ff6f90043875e0bf1d9be6b7d779fc978dc2a5")
+                .unwrap();
+        let creator = hex::decode("9999999999aaaaaaaaaabbbbbbbbbbcccccccccc").unwrap();
+
+        assert_eq!(
+            "5e865d3e45ad3e961f77fd77d46543417ced44d924dc3e079b5415ff6775f847",
+            hex::encode(instantiate2_address(&checksum, &creator, b"a", b"").unwrap())
+        );
+        assert_eq!(
+            "0995499608947a5281e2c7ebd71bdb26a1ad981946dad57f6c4d3ee35de77835",
+            hex::encode(instantiate2_address(&checksum, &creator, b"a", b"{}").unwrap())
+        );
+        assert!(instantiate2_address(&checksum, &creator, b"", b"").is_err());
+        assert!(instantiate2_address(&checksum[1..], &creator, b"a", b"").is_err());
+    }
+
+    #[test]
+    fn roundtrips_bech32_addresses() {
+        let creator = hex::decode("9999999999aaaaaaaaaabbbbbbbbbbcccccccccc").unwrap();
+        let address = encode_address("juno", &creator).unwrap();
+        assert!(address.starts_with("juno1"));
+        assert_eq!(
+            ("juno".to_string(), creator),
+            decode_address(&address).unwrap()
+        );
+        assert!(decode_address("juno1invalid").is_err());
+    }
+}
@@ -0,0 +1,137 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use cargo::core::{Package, Workspace};
+
+use crate::contracts;
+
+const CARGO_LOCK: &str = "Cargo.lock";
+
+/// Returns the contracts affected by changes to the given files,
+/// i.e. the contracts which contain a changed file, or (transitively) depend on a workspace package which does.
+/// A change to the `Cargo.lock` or the root manifest conservatively affects all contracts.
+pub fn affected_contracts<P: AsRef<Path>>(ws: &Workspace, changed: &[P]) -> Vec<Package> {
+    let changed = changed
+        .iter()
+        .map(|path| normalize(path.as_ref()))
+        .collect::<BTreeSet<_>>();
+
+    let all_contracts = contracts(ws);
+    if changed.contains(&normalize(&ws.root().join(CARGO_LOCK)))
+        || changed.contains(&normalize(ws.root_manifest()))
+    {
+        return all_contracts;
+    }
+
+    let mut affected = ws
+        .members()
+        .filter(|member| changed.iter().any(|path| contains(ws, member, path)))
+        .map(|member| member.name().to_string())
+        .collect::<BTreeSet<_>>();
+
+    // walk the dependency graph in reverse, from the changed packages to their dependents
+    let dependents = dependents(ws);
+    let mut queue = affected.iter().cloned().collect::<Vec<_>>();
+    while let Some(name) = queue.pop() {
+        for dependent in dependents.get(&name).into_iter().flatten() {
+            if affected.insert(dependent.clone()) {
+                queue.push(dependent.clone());
+            }
+        }
+    }
+
+    all_contracts
+        .into_iter()
+        .filter(|contract| affected.contains(contract.name().as_str()))
+        .collect()
+}
+
+/// Returns the root dirs of the package & its path dependencies.
+pub(crate) fn package_roots(ws: &Workspace, package: &Package) -> BTreeSet<PathBuf> {
+    let mut roots = BTreeSet::new();
+    collect_package_roots(ws, package, &mut roots);
+    roots
+}
+
+/// Recursively collects the root dirs of the package & its path dependencies.
+fn collect_package_roots(ws: &Workspace, package: &Package, roots: &mut BTreeSet<PathBuf>) {
+    if !roots.insert(package.root().to_path_buf()) {
+        return;
+    }
+
+    package
+        .dependencies()
+        .iter()
+        .filter(|dep| dep.source_id().is_path())
+        .filter_map(|dep| dep.source_id().url().to_file_path().ok())
+        .for_each(|dep_root| {
+            match ws.members().find(|member| member.root() == dep_root) {
+                Some(member) => collect_package_roots(ws, member, roots),
+                // not a workspace member, so its own path dependencies are unknown
+                None => {
+                    roots.insert(dep_root);
+                }
+            }
+        });
+}
+
+/// Returns whether the path belongs to the workspace member,
+/// either directly or through a path dependency outside the workspace.
+/// Paths of nested workspace members only belong to the innermost one.
+fn contains(ws: &Workspace, member: &Package, path: &Path) -> bool {
+    let root = normalize(member.root());
+    let in_member = path.starts_with(&root)
+        && !ws.members().any(|other| {
+            let other_root = normalize(other.root());
+            other_root != root && other_root.starts_with(&root) && path.starts_with(&other_root)
+        });
+
+    in_member
+        || member
+            .dependencies()
+            .iter()
+            .filter(|dep| dep.source_id().is_path())
+            .filter_map(|dep| dep.source_id().url().to_file_path().ok())
+            .filter(|dep_root| !ws.members().any(|other| other.root() == dep_root))
+            .any(|dep_root| path.starts_with(normalize(&dep_root)))
+}
+
+/// Returns the names of the workspace members which directly depend on each workspace member.
+fn dependents(ws: &Workspace) -> BTreeMap<String, Vec<String>> {
+    let members = ws
+        .members()
+        .map(|member| member.name().to_string())
+        .collect::<BTreeSet<_>>();
+
+    let mut dependents = BTreeMap::<String, Vec<String>>::new();
+    for member in ws.members() {
+        member
+            .dependencies()
+            .iter()
+            .filter(|dep| dep.source_id().is_path())
+            .map(|dep| dep.package_name().to_string())
+            .filter(|dep| members.contains(dep))
+            .for_each(|dep| {
+                dependents
+                    .entry(dep)
+                    .or_default()
+                    .push(member.name().to_string())
+            });
+    }
+
+    dependents
+}
+
+/// Resolves symlinks, so that paths reported by git & the file system watcher match the workspace paths.
+/// Paths which no longer exist are resolved through their parent dir.
+fn normalize(path: &Path) -> PathBuf {
+    fs::canonicalize(path)
+        .or_else(|e| match (path.parent(), path.file_name()) {
+            (Some(parent), Some(name)) => fs::canonicalize(parent).map(|parent| parent.join(name)),
+            _ => Err(e),
+        })
+        .unwrap_or_else(|_| path.to_path_buf())
+}
@@ -0,0 +1,308 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Result};
+use cargo::{core::Workspace, util::Filesystem};
+use rustc_demangle::try_demangle;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    compilation::unstripped_config,
+    compile_contracts, contracts,
+    ext::TakeExt,
+    find_manifest,
+    optimization::{optimize_preserving_names, optimized_output_path},
+    wasm::function_sizes,
+    Options, ARTIFACTS,
+};
+
+const TARGET: &str = "target";
+/// Crate of the functions which aren't Rust symbols, e.g. entry points & compiler intrinsics.
+const UNKNOWN_CRATE: &str = "[unknown]";
+/// Crate of the functions implemented for primitive types.
+const STD_CRATE: &str = "std";
+const PRIMITIVES: [&str; 17] = [
+    "bool", "char", "str", "f32", "f64", "i8", "i16", "i32", "i64", "i128", "isize", "u8", "u16",
+    "u32", "u64", "u128", "usize",
+];
+
+/// Attribution of the code size of each contract to functions & crates.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Analysis {
+    pub contracts: Vec<ContractAnalysis>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContractAnalysis {
+    /// Filename of the analyzed artifact.
+    pub artifact: String,
+    /// Total size of the artifact.
+    pub size: u64,
+    /// Total size of all function bodies.
+    pub code_size: u64,
+    /// The largest functions, in descending order of size.
+    pub functions: Vec<FunctionSize>,
+    /// All crates, in descending order of size.
+    pub crates: Vec<CrateSize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FunctionSize {
+    /// Demangled name without the hash.
+    pub name: String,
+    #[serde(rename = "crate")]
+    pub krate: String,
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CrateSize {
+    pub name: String,
+    pub size: u64,
+    pub functions: usize,
+}
+
+/// Builds the contracts with their symbols kept, optimizes them while preserving the names section,
+/// and attributes their code size to functions & crates, either as tables or as JSON.
+pub fn analyze<P: AsRef<Path> + TakeExt<PathBuf>>(
+    workspace_path: P,
+    packages: &[String],
+    top: usize,
+    json: bool,
+    options: &Options,
+) -> Result<()> {
+    let manifest_path = find_manifest(&workspace_path)?;
+    let cfg = unstripped_config(&manifest_path, !options.no_remap_path_prefix)?;
+
+    let dir = tempfile::tempdir()?;
+    let target_dir = Filesystem::new(dir.path().join(TARGET));
+    let output_dir = dir.path().join(ARTIFACTS);
+    fs::create_dir_all(&output_dir)?;
+
+    let mut ws = Workspace::new(manifest_path.as_path(), &cfg)?;
+    ws.set_target_dir(target_dir.clone());
+    let contracts = contracts(&ws)
+        .into_iter()
+        .filter(|p| packages.is_empty() || packages.iter().any(|name| p.name() == name.as_str()))
+        .collect::<Vec<_>>();
+    if contracts.is_empty() {
+        return Err(anyhow!("No CW contracts found. Exiting."));
+    }
+
+    // keep stdout clean for the JSON
+    if !json {
+        println!(
+            "🧐️  Building .../{} with symbols",
+            &manifest_path.rtake(2).display()
+        );
+    }
+    let mut analysis = Analysis::default();
+    for wasm in compile_contracts(&cfg, &ws, &contracts, Some(target_dir))? {
+        let output_path = optimized_output_path(&wasm.path, &output_dir)?;
+        optimize_preserving_names(&wasm.path, &output_path)?;
+
+        let artifact = output_path.rtake(1).display().to_string();
+        let optimized = fs::read(&output_path)?;
+        analysis.contracts.push(analyze_module(
+            &artifact,
+            optimized.len() as u64,
+            function_sizes(&optimized)?,
+            top,
+        ));
+    }
+    analysis
+        .contracts
+        .sort_by(|a, b| a.artifact.cmp(&b.artifact));
+
+    match json {
+        true => println!("{}", serde_json::to_string_pretty(&analysis)?),
+        false => print_analysis(&analysis),
+    }
+
+    Ok(())
+}
+
+/// Attributes the function sizes to their demangled names & crates, and keeps the `top` largest functions.
+pub fn analyze_module(
+    artifact: &str,
+    size: u64,
+    function_sizes: Vec<(String, usize)>,
+    top: usize,
+) -> ContractAnalysis {
+    let mut functions = function_sizes
+        .into_iter()
+        .map(|(symbol, size)| {
+            let (name, krate) = demangle(&symbol);
+            FunctionSize {
+                name,
+                krate,
+                size: size as u64,
+            }
+        })
+        .collect::<Vec<_>>();
+    functions.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.name.cmp(&b.name)));
+
+    let mut crates = BTreeMap::<&str, CrateSize>::new();
+    for function in &functions {
+        let krate = crates.entry(&function.krate).or_insert_with(|| CrateSize {
+            name: function.krate.clone(),
+            size: 0,
+            functions: 0,
+        });
+        krate.size += function.size;
+        krate.functions += 1;
+    }
+    let mut crates = crates.into_values().collect::<Vec<_>>();
+    crates.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.name.cmp(&b.name)));
+
+    let code_size = functions.iter().map(|f| f.size).sum();
+    functions.truncate(top);
+
+    ContractAnalysis {
+        artifact: artifact.to_string(),
+        size,
+        code_size,
+        functions,
+        crates,
+    }
+}
+
+/// Demangles the symbol without its hash, and returns it along with the crate it originates from.
+/// Trait implementations are attributed to the crate of the implementing type, e.g. `<a::A as b::B>::f` to `a`.
+pub fn demangle(symbol: &str) -> (String, String) {
+    let name = match try_demangle(symbol) {
+        Ok(demangled) => format!("{:#}", demangled),
+        Err(_) => return (symbol.to_string(), UNKNOWN_CRATE.to_string()),
+    };
+
+    let path = name
+        .trim_start_matches('<')
+        .trim_start_matches(['&', '*', '[', '('])
+        .trim_start_matches("mut ")
+        .trim_start_matches("const ")
+        .trim_start_matches("dyn ");
+    let krate = path
+        .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .next()
+        .filter(|krate| !krate.is_empty())
+        .map(|krate| match PRIMITIVES.contains(&krate) {
+            true => STD_CRATE,
+            false => krate,
+        })
+        .unwrap_or(UNKNOWN_CRATE)
+        .to_string();
+
+    (name, krate)
+}
+
+fn print_analysis(analysis: &Analysis) {
+    for contract in &analysis.contracts {
+        println!(
+            "📊  {} ({} bytes, of which {} bytes of code)",
+            contract.artifact, contract.size, contract.code_size
+        );
+        let share = |size: u64| match contract.code_size {
+            0 => 0.0,
+            code_size => size as f64 / code_size as f64 * 100.0,
+        };
+
+        println!("    Top functions:");
+        println!(
+            "       {:>10} {:>7}  {:<20} function",
+            "size", "share", "crate"
+        );
+        for function in &contract.functions {
+            println!(
+                "    ...{:>10} {:>6.1}%  {:<20} {}",
+                function.size,
+                share(function.size),
+                function.krate,
+                function.name
+            );
+        }
+
+        println!("    Crates:");
+        println!(
+            "       {:>10} {:>7}  {:>9}  crate",
+            "size", "share", "functions"
+        );
+        for krate in &contract.crates {
+            println!(
+                "    ...{:>10} {:>6.1}%  {:>9}  {}",
+                krate.size,
+                share(krate.size),
+                krate.functions,
+                krate.name
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::analysis::{analyze_module, demangle};
+
+    #[test]
+    fn attributes_symbols_to_crates() {
+        assert_eq!(
+            (
+                "serde_json::de::from_slice".to_string(),
+                "serde_json".to_string()
+            ),
+            demangle("_ZN10serde_json2de10from_slice17h0123456789abcdefE")
+        );
+        assert_eq!(
+            (
+                "<cw20_base::msg::ExecuteMsg as serde::de::Deserialize>::deserialize".to_string(),
+                "cw20_base".to_string()
+            ),
+            demangle("_ZN69_$LT$cw20_base..msg..ExecuteMsg$u20$as$u20$serde..de..Deserialize$GT$11deserialize17h0123456789abcdefE")
+        );
+        assert_eq!(
+            (
+                "<u64 as core::fmt::Display>::fmt".to_string(),
+                "std".to_string()
+            ),
+            demangle("_ZN42_$LT$u64$u20$as$u20$core..fmt..Display$GT$3fmt17h0123456789abcdefE")
+        );
+        assert_eq!(
+            ("instantiate".to_string(), "[unknown]".to_string()),
+            demangle("instantiate")
+        );
+    }
+
+    #[test]
+    fn sums_sizes_per_crate() {
+        let analysis = analyze_module(
+            "a.wasm",
+            100,
+            vec![
+                ("_ZN4core3fmt5write17h0123456789abcdefE".to_string(), 30),
+                ("_ZN4core3str4iter17h0123456789abcdefE".to_string(), 20),
+                ("instantiate".to_string(), 40),
+            ],
+            2,
+        );
+
+        assert_eq!(90, analysis.code_size);
+        assert_eq!(
+            vec!["instantiate", "core::fmt::write"],
+            analysis
+                .functions
+                .iter()
+                .map(|f| f.name.as_str())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![("core", 50, 2), ("[unknown]", 40, 1)],
+            analysis
+                .crates
+                .iter()
+                .map(|c| (c.name.as_str(), c.size, c.functions))
+                .collect::<Vec<_>>()
+        );
+    }
+}
@@ -0,0 +1,349 @@
+use std::{collections::BTreeMap, fs, path::PathBuf};
+
+use anyhow::{anyhow, Context, Result};
+use cargo::core::{Package, Workspace};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    comparison::{diff_modules, ArtifactDiff},
+    compatibility::{compare_contracts, print_changes, read_schema_dir, SchemaChange},
+    compilation::config,
+    compile_contracts, contracts,
+    ext::TakeExt,
+    git::TempWorktree,
+    hashing::sha256_digest,
+    optimization::{optimize, optimized_output_path, OptimizedWasm},
+    schema::{generate_schemas, SCHEMA},
+    Options, ARTIFACTS,
+};
+
+/// Changes of the artifacts between a git baseline & the working tree.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BaselineReport {
+    /// The git ref of the baseline.
+    pub baseline: String,
+    /// The commit the git ref resolved to.
+    pub commit: String,
+    /// Entries sorted by artifact name.
+    pub contracts: Vec<ContractChange>,
+    /// Changes of the message schemas, if they were generated.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub schema_changes: Vec<SchemaChange>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContractChange {
+    /// Filename of the optimized artifact.
+    pub artifact: String,
+    /// Size of the baseline artifact, unless the contract is new.
+    pub baseline_size: Option<u64>,
+    /// Size of the current artifact, unless the contract was removed.
+    pub size: Option<u64>,
+    pub baseline_checksum: Option<String>,
+    pub checksum: Option<String>,
+    /// Changes of the entry points, capabilities, features, imports & interface version.
+    pub interface_changes: Vec<String>,
+}
+
+impl ContractChange {
+    /// Difference of the size to the baseline size, in bytes.
+    pub fn delta(&self) -> Option<i64> {
+        self.baseline_size
+            .zip(self.size)
+            .map(|(baseline, size)| size as i64 - baseline as i64)
+    }
+
+    fn status(&self) -> String {
+        match (&self.baseline_checksum, &self.checksum) {
+            (None, _) => String::from("new"),
+            (_, None) => String::from("removed"),
+            (baseline, checksum) if baseline == checksum => String::from("unchanged"),
+            _ => String::from("changed"),
+        }
+    }
+}
+
+/// Builds the contracts at the git ref in a temporary worktree, sharing the workspace's target dir,
+/// and reports how the optimized artifacts changed since. The report is written as Markdown if a path is given.
+/// If `partial` is set, then only the given packages are compared.
+pub fn compare_with_baseline(
+    ws: &Workspace,
+    packages: &[Package],
+    optimized_wasms: &[OptimizedWasm],
+    git_ref: &str,
+    partial: bool,
+    options: &Options,
+) -> Result<BaselineReport> {
+    let worktree = TempWorktree::add(ws.root(), git_ref)?;
+    println!(
+        "🧐️  Compiling the baseline {} ({})",
+        git_ref,
+        &worktree.commit[..12]
+    );
+
+    let manifest_path = worktree.relocate(ws.root_manifest())?;
+    if !manifest_path.exists() {
+        return Err(anyhow!(
+            "The baseline {} has no manifest at {}",
+            git_ref,
+            manifest_path.rtake(2).display()
+        ));
+    }
+    let cfg = config(&manifest_path, !options.no_remap_path_prefix)?;
+    let mut baseline_ws = Workspace::new(manifest_path.as_path(), &cfg)?;
+    let target_dir = ws.target_dir();
+    baseline_ws.set_target_dir(target_dir.clone());
+    let baseline_contracts = contracts(&baseline_ws)
+        .into_iter()
+        .filter(|p| !partial || packages.iter().any(|c| c.name() == p.name()))
+        .collect::<Vec<_>>();
+
+    let output_dir = worktree.path.join(ARTIFACTS);
+    fs::create_dir_all(&output_dir)?;
+    let mut baseline = BTreeMap::new();
+    for wasm in compile_contracts(&cfg, &baseline_ws, &baseline_contracts, Some(target_dir))? {
+        let output_path = optimized_output_path(&wasm.path, &output_dir)?;
+        optimize(&wasm.path, &output_path)?;
+        baseline.insert(
+            output_path.rtake(1).display().to_string(),
+            fs::read(&output_path)?,
+        );
+    }
+
+    let current = optimized_wasms
+        .iter()
+        .map(|wasm| {
+            let wasm_path = &wasm.path;
+            let artifact =
+                fs::read(wasm_path).context(format!("Failed read from {}", wasm_path.display()))?;
+            Ok((wasm_path.rtake(1).display().to_string(), artifact))
+        })
+        .collect::<Result<BTreeMap<_, _>>>()?;
+
+    let schema_changes = match options.schema {
+        true => {
+            generate_schemas(&cfg, &baseline_contracts, &output_dir)?;
+            compare_contracts(
+                &read_schema_dir(&output_dir.join(SCHEMA))?,
+                &read_schema_dir(&ws.root().join(ARTIFACTS).join(SCHEMA))?,
+            )
+        }
+        false => vec![],
+    };
+
+    let report = BaselineReport {
+        baseline: git_ref.to_string(),
+        commit: worktree.commit.clone(),
+        contracts: compare_artifacts(&baseline, &current)?,
+        schema_changes,
+    };
+    print_report(&report);
+    if let Some(path) = &options.baseline_markdown {
+        write_markdown(&report, path)?;
+    }
+
+    Ok(report)
+}
+
+/// Compares the baseline artifacts to the current ones, both by artifact filename.
+pub fn compare_artifacts(
+    baseline: &BTreeMap<String, Vec<u8>>,
+    current: &BTreeMap<String, Vec<u8>>,
+) -> Result<Vec<ContractChange>> {
+    let mut artifacts = baseline.keys().chain(current.keys()).collect::<Vec<_>>();
+    artifacts.sort();
+    artifacts.dedup();
+
+    artifacts
+        .into_iter()
+        .map(|artifact| {
+            let (old, new) = (baseline.get(artifact), current.get(artifact));
+            let interface_changes = match (old, new) {
+                (Some(old), Some(new)) if old != new => interface_changes(&diff_modules(old, new)?),
+                _ => vec![],
+            };
+            let checksum =
+                |wasm: Option<&Vec<u8>>| wasm.map(|w| sha256_digest(w.as_slice())).transpose();
+
+            Ok(ContractChange {
+                artifact: artifact.clone(),
+                baseline_size: old.map(|wasm| wasm.len() as u64),
+                size: new.map(|wasm| wasm.len() as u64),
+                baseline_checksum: checksum(old)?,
+                checksum: checksum(new)?,
+                interface_changes,
+            })
+        })
+        .collect()
+}
+
+/// Describes the interface changes, e.g. `+capability stargate` or `-entry point migrate`.
+fn interface_changes(artifact_diff: &ArtifactDiff) -> Vec<String> {
+    let mut changes = vec![];
+    let lists = [
+        ("entry point", &artifact_diff.entry_points),
+        ("capability", &artifact_diff.capabilities),
+        ("feature", &artifact_diff.features),
+        ("import", &artifact_diff.imports),
+    ];
+    for (kind, list) in lists {
+        changes.extend(list.added.iter().map(|i| format!("+{} {}", kind, i)));
+        changes.extend(list.removed.iter().map(|i| format!("-{} {}", kind, i)));
+    }
+    if artifact_diff.old_interface_version != artifact_diff.new_interface_version {
+        changes.push(format!(
+            "interface version {} -> {}",
+            artifact_diff
+                .old_interface_version
+                .as_deref()
+                .unwrap_or("unknown"),
+            artifact_diff
+                .new_interface_version
+                .as_deref()
+                .unwrap_or("unknown")
+        ));
+    }
+
+    changes
+}
+
+fn print_report(report: &BaselineReport) {
+    println!("⚖️  Changes since {}:", report.baseline);
+    println!(
+        "       {:<40} {:>10} {:>10} {:>10} {:>10}  interface",
+        "artifact", "baseline", "current", "delta", "checksum"
+    );
+    for contract in &report.contracts {
+        let size = |size: Option<u64>| size.map_or_else(|| "-".into(), |s| s.to_string());
+        let delta = contract
+            .delta()
+            .map_or_else(|| "-".into(), |d| format!("{:+}", d));
+        println!(
+            "    ...{:<40} {:>10} {:>10} {:>10} {:>10}  {}",
+            contract.artifact,
+            size(contract.baseline_size),
+            size(contract.size),
+            delta,
+            contract.status(),
+            contract.interface_changes.join(", ")
+        );
+    }
+    if !report.schema_changes.is_empty() {
+        println!("🧬  Schema changes since {}:", report.baseline);
+        print_changes(&report.schema_changes);
+    }
+}
+
+/// Renders the report as a Markdown table, e.g. for a PR comment.
+pub fn render_markdown(report: &BaselineReport) -> String {
+    let size = |size: Option<u64>| size.map_or_else(|| "-".into(), |s| format!("{} B", s));
+    let mut markdown = format!(
+        "### Contract changes since `{}` ({})\n\n\
+        | Contract | Baseline | Current | Δ | Checksum | Interface |\n\
+        | --- | ---: | ---: | ---: | --- | --- |\n",
+        report.baseline,
+        &report.commit[..report.commit.len().min(12)]
+    );
+    for contract in &report.contracts {
+        let delta = match contract.delta() {
+            Some(0) => String::from("0"),
+            Some(delta) => format!("{:+} B", delta),
+            None => String::from("-"),
+        };
+        let interface = match contract.interface_changes.is_empty() {
+            true => String::from("-"),
+            false => contract
+                .interface_changes
+                .iter()
+                .map(|change| format!("`{}`", change))
+                .collect::<Vec<_>>()
+                .join("<br>"),
+        };
+        markdown.push_str(&format!(
+            "| `{}` | {} | {} | {} | {} | {} |\n",
+            contract.artifact,
+            size(contract.baseline_size),
+            size(contract.size),
+            delta,
+            contract.status(),
+            interface
+        ));
+    }
+    if !report.schema_changes.is_empty() {
+        markdown.push_str(
+            "\n#### Schema changes\n\n\
+            | Contract | Message | Change | Compatibility |\n\
+            | --- | --- | --- | --- |\n",
+        );
+        for change in &report.schema_changes {
+            markdown.push_str(&format!(
+                "| `{}` | `{}` | {} | {} |\n",
+                change.contract, change.path, change.change, change.compatibility
+            ));
+        }
+    }
+
+    markdown
+}
+
+fn write_markdown(report: &BaselineReport, path: &PathBuf) -> Result<()> {
+    fs::write(path, render_markdown(report))
+        .context(format!("Failed write into {}", path.display()))?;
+    println!("    ...📝 Saved the report to {}", path.display());
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        baseline::{render_markdown, BaselineReport, ContractChange},
+        compatibility::{Compatibility, SchemaChange},
+    };
+
+    #[test]
+    fn renders_markdown_tables() {
+        let report = BaselineReport {
+            baseline: "main".to_string(),
+            commit: "0123456789abcdef0123".to_string(),
+            contracts: vec![
+                ContractChange {
+                    artifact: "a.wasm".to_string(),
+                    baseline_size: Some(1_000),
+                    size: Some(4_072),
+                    baseline_checksum: Some("aa".to_string()),
+                    checksum: Some("bb".to_string()),
+                    interface_changes: vec![
+                        "+capability stargate".to_string(),
+                        "-entry point migrate".to_string(),
+                    ],
+                },
+                ContractChange {
+                    artifact: "b.wasm".to_string(),
+                    size: Some(500),
+                    checksum: Some("cc".to_string()),
+                    ..ContractChange::default()
+                },
+            ],
+            schema_changes: vec![SchemaChange {
+                contract: "a".to_string(),
+                path: "execute.transfer".to_string(),
+                change: "removes the field memo".to_string(),
+                compatibility: Compatibility::Breaking,
+            }],
+        };
+
+        assert_eq!(
+            "### Contract changes since `main` (0123456789ab)\n\n\
+            | Contract | Baseline | Current | Δ | Checksum | Interface |\n\
+            | --- | ---: | ---: | ---: | --- | --- |\n\
+            | `a.wasm` | 1000 B | 4072 B | +3072 B | changed | `+capability stargate`<br>`-entry point migrate` |\n\
+            | `b.wasm` | - | 500 B | - | new | - |\n\
+            \n#### Schema changes\n\n\
+            | Contract | Message | Change | Compatibility |\n\
+            | --- | --- | --- | --- |\n\
+            | `a` | `execute.transfer` | removes the field memo | breaking |\n",
+            render_markdown(&report)
+        );
+    }
+}
@@ -0,0 +1,303 @@
+use std::{collections::BTreeMap, fmt};
+
+use anyhow::{anyhow, Result};
+use cargo::core::{Package, Workspace};
+use serde::Deserialize;
+
+use crate::{
+    hashing::{ArtifactChecksums, ChecksumsManifest},
+    METADATA_KEY,
+};
+
+const SIZE_BUDGET_KEY: &str = "size-budget";
+
+/// Size budget of a contract, configured in `[workspace.metadata.cw-optimizoor.size-budget]`
+/// & overridden in `[package.metadata.cw-optimizoor.size-budget]`.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SizeBudget {
+    /// Budget of the raw artifact.
+    #[serde(default)]
+    pub raw: Budget,
+    /// Budget of the gzipped artifact.
+    #[serde(default)]
+    pub gzip: Budget,
+}
+
+/// Warning & error thresholds, both absolute in bytes & relative to the previous artifact in percent.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Budget {
+    pub warn: Option<u64>,
+    pub error: Option<u64>,
+    pub warn_growth: Option<f64>,
+    pub error_growth: Option<f64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Raw,
+    Gzip,
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Kind::Raw => write!(f, "raw"),
+            Kind::Gzip => write!(f, "gzip"),
+        }
+    }
+}
+
+/// An artifact exceeding its budget.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    pub artifact: String,
+    pub kind: Kind,
+    pub level: Level,
+    pub size: u64,
+    /// The exceeded limit in bytes.
+    pub limit: u64,
+    /// The budget, e.g. `800000 B` or `+5% of 512000 B`.
+    pub budget: String,
+}
+
+impl Budget {
+    /// Overrides the thresholds with the ones set in `other`.
+    fn merge(&self, other: &Budget) -> Budget {
+        Budget {
+            warn: other.warn.or(self.warn),
+            error: other.error.or(self.error),
+            warn_growth: other.warn_growth.or(self.warn_growth),
+            error_growth: other.error_growth.or(self.error_growth),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self == &Budget::default()
+    }
+}
+
+/// Returns the size budgets of the contracts by package name, if any are configured.
+pub fn size_budgets(ws: &Workspace, contracts: &[Package]) -> Result<BTreeMap<String, SizeBudget>> {
+    let default = parse_size_budget(ws.custom_metadata())?.unwrap_or_default();
+
+    contracts
+        .iter()
+        .map(|p| {
+            let budget = match parse_size_budget(p.manifest().custom_metadata())? {
+                Some(budget) => SizeBudget {
+                    raw: default.raw.merge(&budget.raw),
+                    gzip: default.gzip.merge(&budget.gzip),
+                },
+                None => default.clone(),
+            };
+            Ok((p.name().to_string(), budget))
+        })
+        .filter(|res| {
+            !matches!(res, Ok((_, budget)) if budget.raw.is_empty() && budget.gzip.is_empty())
+        })
+        .collect()
+}
+
+/// Parses the size budget from the `cw-optimizoor` table of the metadata.
+fn parse_size_budget(metadata: Option<&toml::Value>) -> Result<Option<SizeBudget>> {
+    metadata
+        .and_then(|metadata| metadata.get(METADATA_KEY))
+        .and_then(|metadata| metadata.get(SIZE_BUDGET_KEY))
+        .map(|budget| {
+            budget
+                .clone()
+                .try_into()
+                .map_err(|e| anyhow!("Invalid {}.{}: {}", METADATA_KEY, SIZE_BUDGET_KEY, e))
+        })
+        .transpose()
+}
+
+/// Compares the artifacts to their budgets, and to their previous sizes.
+pub fn check_budgets(
+    budgets: &BTreeMap<String, SizeBudget>,
+    artifacts: &[ArtifactChecksums],
+    previous: &ChecksumsManifest,
+) -> Vec<Violation> {
+    let mut violations = vec![];
+    for artifact in artifacts {
+        let Some(budget) = budgets.get(&artifact.package) else {
+            continue;
+        };
+        let prev = previous.artifacts.iter().find(|a| a.name == artifact.name);
+        let sizes = [
+            (Kind::Raw, &budget.raw, artifact.size, prev.map(|p| p.size)),
+            (
+                Kind::Gzip,
+                &budget.gzip,
+                artifact.gzip_size,
+                prev.map(|p| p.gzip_size),
+            ),
+        ];
+
+        for (kind, budget, size, prev_size) in sizes {
+            // an error supersedes a warning of the same kind
+            let violation = [Level::Error, Level::Warning]
+                .into_iter()
+                .find_map(|level| {
+                    let (absolute, growth) = match level {
+                        Level::Error => (budget.error, budget.error_growth),
+                        Level::Warning => (budget.warn, budget.warn_growth),
+                    };
+                    let absolute = absolute.map(|limit| (limit, format!("{} B", limit)));
+                    let relative = growth.zip(prev_size).map(|(growth, prev_size)| {
+                        let limit = (prev_size as f64 * (1.0 + growth / 100.0)) as u64;
+                        (limit, format!("+{}% of {} B", growth, prev_size))
+                    });
+
+                    absolute
+                        .into_iter()
+                        .chain(relative)
+                        .filter(|(limit, _)| size > *limit)
+                        .min_by_key(|(limit, _)| *limit)
+                        .map(|(limit, budget)| Violation {
+                            artifact: artifact.name.clone(),
+                            kind,
+                            level,
+                            size,
+                            limit,
+                            budget,
+                        })
+                });
+            violations.extend(violation);
+        }
+    }
+
+    violations
+}
+
+/// Prints a table of the violations, and fails if any of them is an error.
+pub fn report_violations(violations: &[Violation]) -> Result<()> {
+    if violations.is_empty() {
+        return Ok(());
+    }
+
+    println!("📏  Size budgets exceeded:");
+    println!(
+        "           {:<40} {:<5} {:>10} {:>24} {:>12}",
+        "artifact", "kind", "size", "budget", "exceeded by"
+    );
+    for violation in violations {
+        let icon = match violation.level {
+            Level::Warning => "⚠️ ",
+            Level::Error => "❌",
+        };
+        println!(
+            "    ...{} {:<40} {:<5} {:>10} {:>24} {:>12}",
+            icon,
+            violation.artifact,
+            violation.kind,
+            violation.size,
+            violation.budget,
+            violation.size - violation.limit
+        );
+    }
+
+    let errors = violations
+        .iter()
+        .filter(|v| v.level == Level::Error)
+        .count();
+    if errors > 0 {
+        return Err(anyhow!("{} artifact(s) exceeded their size budget", errors));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use crate::{
+        budget::{check_budgets, parse_size_budget, Budget, Kind, Level, SizeBudget},
+        hashing::{ArtifactChecksums, ChecksumsManifest},
+    };
+
+    fn artifact(size: u64, gzip_size: u64) -> ArtifactChecksums {
+        ArtifactChecksums {
+            name: "a.wasm".to_string(),
+            package: "a".to_string(),
+            version: "0.1.0".to_string(),
+            size,
+            gzip_size,
+            digests: BTreeMap::new(),
+            gzip: None,
+            schemas: BTreeMap::new(),
+        }
+    }
+
+    #[test]
+    fn parses_size_budgets_from_metadata() {
+        let metadata = toml::from_str::<toml::Value>(
+            r#"
+            [cw-optimizoor.size-budget]
+            raw = { warn = 600000, error = 800000 }
+            gzip = { error-growth = 5.0 }
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            Some(SizeBudget {
+                raw: Budget {
+                    warn: Some(600_000),
+                    error: Some(800_000),
+                    ..Budget::default()
+                },
+                gzip: Budget {
+                    error_growth: Some(5.0),
+                    ..Budget::default()
+                },
+            }),
+            parse_size_budget(Some(&metadata)).unwrap()
+        );
+        assert_eq!(None, parse_size_budget(None).unwrap());
+    }
+
+    #[test]
+    fn checks_absolute_and_relative_budgets() {
+        let budgets = BTreeMap::from([(
+            "a".to_string(),
+            SizeBudget {
+                raw: Budget {
+                    warn: Some(900),
+                    error: Some(1_000),
+                    ..Budget::default()
+                },
+                gzip: Budget {
+                    warn_growth: Some(10.0),
+                    ..Budget::default()
+                },
+            },
+        )]);
+        let previous = ChecksumsManifest {
+            artifacts: vec![artifact(800, 400)],
+        };
+
+        assert!(check_budgets(&budgets, &[artifact(850, 440)], &previous).is_empty());
+
+        let violations = check_budgets(&budgets, &[artifact(1_100, 450)], &previous);
+        assert_eq!(
+            vec![
+                (Kind::Raw, Level::Error, 1_000, "1000 B"),
+                (Kind::Gzip, Level::Warning, 440, "+10% of 400 B"),
+            ],
+            violations
+                .iter()
+                .map(|v| (v.kind, v.level, v.limit, v.budget.as_str()))
+                .collect::<Vec<_>>()
+        );
+    }
+}
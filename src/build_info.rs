@@ -0,0 +1,237 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use cargo::{
+    core::{profiles::Profiles, Workspace},
+    util::interning::InternedString,
+    Config,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    compilation::{recorded_rustflags, CompiledWasm, PROFILE_RELEASE, TARGET_WASM32},
+    ext::TakeExt,
+    git,
+    hashing::file_sha256_digest,
+    optimization::{OptimizedWasm, OPTIMIZATION_LEVEL, SHRINK_LEVEL},
+    schema::schema_digests,
+};
+
+pub const BUILD_INFO: &str = "build-info.json";
+const CARGO_LOCK: &str = "Cargo.lock";
+const PKG_NAME: &str = env!("CARGO_PKG_NAME");
+const PKG_VERSION: &str = env!("CARGO_PKG_VERSION");
+const OPTIMIZER_BACKEND: &str = "binaryen";
+
+/// Provenance of the artifacts: the toolchain, settings & sources they were built with.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BuildInfo {
+    pub tool: Tool,
+    /// Verbose version of rustc, e.g. `rustc 1.69.0 (84c898d65 2023-04-16)`.
+    pub rustc: String,
+    /// Commit of the rust repository rustc was built from.
+    pub rustc_commit_hash: Option<String>,
+    /// Version of the cargo library used for building.
+    pub cargo: String,
+    pub target: String,
+    pub rustflags: Vec<String>,
+    /// Settings of the release profile.
+    pub profile: serde_json::Value,
+    pub optimizer: Optimizer,
+    /// SHA-256 of the `Cargo.lock`.
+    pub cargo_lock_sha256: Option<String>,
+    pub git: Option<GitRevision>,
+    pub contracts: Vec<ContractBuildInfo>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Tool {
+    pub name: String,
+    pub version: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Optimizer {
+    pub backend: String,
+    pub optimization_level: u32,
+    pub shrink_level: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GitRevision {
+    pub commit: String,
+    /// Whether the working tree had uncommitted changes, besides the artifacts.
+    pub dirty: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContractBuildInfo {
+    pub package: String,
+    pub version: String,
+    pub features: Vec<String>,
+    /// Filename of the optimized artifact.
+    pub artifact: String,
+    pub intermediate_sha256: String,
+    pub sha256: String,
+    /// SHA-256 digests of the contract's schema files by artifact name, if they were generated.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub schemas: BTreeMap<String, String>,
+}
+
+/// Records the provenance of the optimized artifacts & schema files into `build-info.json` in the output dir,
+/// and returns it. If `partial` is set, then the records of any other contracts are kept.
+pub fn write_build_info(
+    cfg: &Config,
+    ws: &Workspace,
+    compiled_wasms: &[CompiledWasm],
+    optimized_wasms: &[OptimizedWasm],
+    schemas: &BTreeMap<String, Vec<PathBuf>>,
+    output_dir: &Path,
+    partial: bool,
+) -> Result<BuildInfo> {
+    let mut contracts = optimized_wasms
+        .iter()
+        .filter_map(|optimized| {
+            compiled_wasms
+                .iter()
+                .find(|compiled| compiled.path == optimized.intermediate_path)
+                .map(|compiled| (compiled, optimized))
+        })
+        .map(|(compiled, optimized)| {
+            Ok(ContractBuildInfo {
+                package: compiled.package_name.clone(),
+                version: compiled.package_version.clone(),
+                features: compiled.features.clone(),
+                artifact: optimized.path.rtake(1).display().to_string(),
+                intermediate_sha256: file_sha256_digest(&compiled.path)?,
+                sha256: file_sha256_digest(&optimized.path)?,
+                schemas: schema_digests(
+                    schemas
+                        .get(&compiled.package_name)
+                        .map(Vec::as_slice)
+                        .unwrap_or_default(),
+                )?,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let path = output_dir.join(BUILD_INFO);
+    if partial {
+        if let Ok(prev) = read_build_info(&path) {
+            let kept = prev
+                .contracts
+                .into_iter()
+                .filter(|prev| !contracts.iter().any(|c| c.package == prev.package))
+                .collect::<Vec<_>>();
+            contracts.extend(kept);
+        }
+    }
+    contracts.sort_by(|a, b| a.package.cmp(&b.package));
+
+    let rustc = cfg.load_global_rustc(Some(ws))?;
+    let profile = Profiles::new(ws, InternedString::from(PROFILE_RELEASE))?.base_profile();
+    let cargo_lock = ws.root().join(CARGO_LOCK);
+    let build_info = BuildInfo {
+        tool: Tool {
+            name: PKG_NAME.to_string(),
+            version: PKG_VERSION.to_string(),
+        },
+        rustc: rustc
+            .verbose_version
+            .lines()
+            .next()
+            .unwrap_or_default()
+            .to_string(),
+        rustc_commit_hash: rustc
+            .verbose_version
+            .lines()
+            .find_map(|line| line.strip_prefix("commit-hash: "))
+            .filter(|hash| *hash != "unknown")
+            .map(String::from),
+        cargo: cargo::version().to_string(),
+        target: TARGET_WASM32.to_string(),
+        rustflags: recorded_rustflags(),
+        profile: serde_json::to_value(profile)?,
+        optimizer: Optimizer {
+            backend: OPTIMIZER_BACKEND.to_string(),
+            optimization_level: OPTIMIZATION_LEVEL,
+            shrink_level: SHRINK_LEVEL,
+        },
+        cargo_lock_sha256: match cargo_lock.exists() {
+            true => Some(file_sha256_digest(&cargo_lock)?),
+            false => None,
+        },
+        git: git::head_commit(ws.root(), output_dir)
+            .ok()
+            .map(|(commit, dirty)| GitRevision { commit, dirty }),
+        contracts,
+    };
+
+    fs::write(&path, serde_json::to_string_pretty(&build_info)? + "\n")
+        .context(format!("Failed write into {}", path.display()))?;
+
+    Ok(build_info)
+}
+
+/// Reads a `build-info.json`.
+pub fn read_build_info(path: &Path) -> Result<BuildInfo> {
+    let contents =
+        fs::read_to_string(path).context(format!("Failed read from {}", path.display()))?;
+
+    Ok(serde_json::from_str(&contents)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        build_info::{BuildInfo, Optimizer, Tool},
+        compilation::redact_host_paths,
+    };
+
+    #[test]
+    fn records_no_host_paths() {
+        let rustflags = [
+            "-C",
+            "strip=symbols",
+            "--remap-path-prefix=/home/alice/.cargo=/cargo",
+            "--remap-path-prefix=/home/alice/my=ws=/workspace",
+        ]
+        .map(String::from);
+        let build_info = BuildInfo {
+            tool: Tool {
+                name: "cw-optimizoor".to_string(),
+                version: "0.10.0".to_string(),
+            },
+            rustc: "rustc 1.69.0 (84c898d65 2023-04-16)".to_string(),
+            rustc_commit_hash: None,
+            cargo: "0.71.0".to_string(),
+            target: "wasm32-unknown-unknown".to_string(),
+            rustflags: redact_host_paths(&rustflags),
+            profile: serde_json::json!({ "opt_level": "3" }),
+            optimizer: Optimizer {
+                backend: "binaryen".to_string(),
+                optimization_level: 2,
+                shrink_level: 2,
+            },
+            cargo_lock_sha256: None,
+            git: None,
+            contracts: vec![],
+        };
+
+        let json = serde_json::to_string(&build_info).unwrap();
+        assert!(!json.contains("/home"), "{}", json);
+        assert_eq!(
+            vec![
+                "-C",
+                "strip=symbols",
+                "--remap-path-prefix=<cargo>=/cargo",
+                "--remap-path-prefix=<workspace>=/workspace",
+            ],
+            build_info.rustflags
+        );
+    }
+}
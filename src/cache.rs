@@ -0,0 +1,312 @@
+use std::{fs, io::Read, path::Path, time::Duration};
+
+use anyhow::{anyhow, Context, Result};
+use curl::easy::{Easy, List};
+
+use crate::{
+    hashing::sha256_digest,
+    optimization::{OPTIMIZATION_LEVEL, SHRINK_LEVEL},
+};
+
+const TIMEOUT: Duration = Duration::from_secs(30);
+const PKG_NAME: &str = env!("CARGO_PKG_NAME");
+const PKG_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Options for the remote optimization cache.
+#[derive(clap::Args, Debug, Clone, Default)]
+pub struct RemoteCacheOptions {
+    /// Base URL of a remote optimization cache, served over HTTP GET/PUT
+    #[clap(long = "cache-url", env = "CW_OPTIMIZOOR_CACHE_URL")]
+    pub url: Option<String>,
+
+    /// Header sent with each remote cache request, e.g. "Authorization: Bearer <token>"
+    #[clap(
+        long = "cache-auth-header",
+        env = "CW_OPTIMIZOOR_CACHE_AUTH_HEADER",
+        requires = "url"
+    )]
+    pub auth_header: Option<String>,
+
+    /// Only download from the remote cache, never upload to it
+    #[clap(long = "cache-read-only", env = "CW_OPTIMIZOOR_CACHE_READ_ONLY")]
+    pub read_only: bool,
+}
+
+/// A remote cache of optimized WASM artifacts, keyed by the SHA-256 of the intermediate artifact
+/// together with the optimizer fingerprint, i.e. the cw-optimizoor version and the optimizer settings,
+/// since the optimized artifact depends on them as well. The bundled binaryen is pinned by the cw-optimizoor version.
+///
+/// Each entry consists of two objects:
+/// - `{url}/{key}.wasm` - the optimized artifact
+/// - `{url}/{key}.sha256` - the recorded SHA-256 digest of the optimized artifact
+#[derive(Debug, Clone)]
+pub struct RemoteCache {
+    url: String,
+    auth_header: Option<String>,
+    read_only: bool,
+    fingerprint: String,
+}
+
+/// Describes everything besides the intermediate artifact which the optimized artifact depends on.
+pub fn optimizer_fingerprint(tool_version: &str) -> String {
+    format!(
+        "{} {}, -O{} shrink {}",
+        PKG_NAME, tool_version, OPTIMIZATION_LEVEL, SHRINK_LEVEL
+    )
+}
+
+impl RemoteCache {
+    /// Creates the remote cache, if a URL is configured.
+    pub fn new(opts: &RemoteCacheOptions) -> Option<RemoteCache> {
+        opts.url.as_ref().map(|url| RemoteCache {
+            url: url.trim_end_matches('/').to_string(),
+            auth_header: opts.auth_header.clone(),
+            read_only: opts.read_only,
+            fingerprint: optimizer_fingerprint(PKG_VERSION),
+        })
+    }
+
+    /// Returns the key of the optimized artifact for the intermediate artifact.
+    pub fn key(&self, wasm_path: &Path) -> Result<String> {
+        let intermediate_digest = sha256_digest(fs::File::open(wasm_path)?)?;
+        sha256_digest(format!("{}\n{}", self.fingerprint, intermediate_digest).as_bytes())
+    }
+
+    /// Restores the optimized artifact for the intermediate artifact from the cache.
+    /// Returns false on a cache miss.
+    pub fn restore(&self, wasm_path: &Path, output_path: &Path) -> Result<bool> {
+        let key = self.key(wasm_path)?;
+        match self.fetch(&key)? {
+            Some(wasm) => {
+                fs::write(output_path, wasm)
+                    .context(format!("Failed write into {}", output_path.display()))?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Uploads the optimized artifact for the intermediate artifact to the cache.
+    /// Does nothing if the cache is read-only.
+    pub fn upload(&self, wasm_path: &Path, output_path: &Path) -> Result<()> {
+        if self.read_only {
+            return Ok(());
+        }
+
+        let key = self.key(wasm_path)?;
+        let wasm = fs::read(output_path)?;
+        self.store(&key, &wasm)
+    }
+
+    /// Fetches the optimized artifact stored under `key`,
+    /// and verifies it against its recorded digest.
+    pub fn fetch(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        let digest = match self.get(&self.object_url(key, "sha256"))? {
+            Some(digest) => String::from_utf8(digest)?.trim().to_string(),
+            None => return Ok(None),
+        };
+        let wasm = match self.get(&self.object_url(key, "wasm"))? {
+            Some(wasm) => wasm,
+            None => return Ok(None),
+        };
+
+        let actual = sha256_digest(wasm.as_slice())?;
+        if actual != digest {
+            return Err(anyhow!(
+                "digest mismatch for {key}: expected {digest}, got {actual}"
+            ));
+        }
+
+        Ok(Some(wasm))
+    }
+
+    /// Stores the optimized artifact under `key`, along with its digest.
+    pub fn store(&self, key: &str, wasm: &[u8]) -> Result<()> {
+        // the digest is uploaded last, so that an entry is never visible before it's complete
+        let digest = sha256_digest(wasm)?;
+        self.put(&self.object_url(key, "wasm"), wasm)?;
+        self.put(&self.object_url(key, "sha256"), digest.as_bytes())
+    }
+
+    fn object_url(&self, key: &str, ext: &str) -> String {
+        format!("{}/{}.{}", self.url, key, ext)
+    }
+
+    fn get(&self, url: &str) -> Result<Option<Vec<u8>>> {
+        let mut easy = self.easy(url, List::new())?;
+        let mut body = Vec::new();
+        {
+            let mut transfer = easy.transfer();
+            transfer.write_function(|data| {
+                body.extend_from_slice(data);
+                Ok(data.len())
+            })?;
+            transfer.perform()?;
+        }
+
+        match easy.response_code()? {
+            200 => Ok(Some(body)),
+            404 => Ok(None),
+            code => Err(anyhow!("GET {url} returned HTTP {code}")),
+        }
+    }
+
+    fn put(&self, url: &str, mut data: &[u8]) -> Result<()> {
+        // disable `Expect: 100-continue`, which plain object stores don't always honor
+        let mut headers = List::new();
+        headers.append("Expect:")?;
+
+        let mut easy = self.easy(url, headers)?;
+        easy.upload(true)?;
+        easy.in_filesize(data.len() as u64)?;
+        {
+            let mut transfer = easy.transfer();
+            transfer.read_function(|buf| Ok(data.read(buf).unwrap_or(0)))?;
+            transfer.perform()?;
+        }
+
+        match easy.response_code()? {
+            200..=299 => Ok(()),
+            code => Err(anyhow!("PUT {url} returned HTTP {code}")),
+        }
+    }
+
+    fn easy(&self, url: &str, mut headers: List) -> Result<Easy> {
+        if let Some(auth_header) = &self.auth_header {
+            headers.append(auth_header)?;
+        }
+
+        let mut easy = Easy::new();
+        easy.url(url)?;
+        easy.follow_location(true)?;
+        easy.timeout(TIMEOUT)?;
+        easy.http_headers(headers)?;
+
+        Ok(easy)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        fs,
+        io::{BufRead, BufReader, Read, Write},
+        net::TcpListener,
+        sync::{Arc, Mutex},
+        thread,
+    };
+
+    use crate::{
+        cache::{optimizer_fingerprint, RemoteCache, RemoteCacheOptions},
+        hashing::sha256_digest,
+    };
+
+    /// Spawns a minimal HTTP server which serves GET/PUT requests from memory.
+    fn serve(objects: Arc<Mutex<HashMap<String, Vec<u8>>>>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                let mut parts = request_line.split_whitespace();
+                let (method, path) = (parts.next().unwrap(), parts.next().unwrap().to_string());
+
+                let mut content_length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line.trim().is_empty() {
+                        break;
+                    }
+                    if let Some((name, value)) = line.split_once(':') {
+                        if name.eq_ignore_ascii_case("content-length") {
+                            content_length = value.trim().parse().unwrap();
+                        }
+                    }
+                }
+
+                let mut objects = objects.lock().unwrap();
+                let (status, body) = match method {
+                    "PUT" => {
+                        let mut body = vec![0; content_length];
+                        reader.read_exact(&mut body).unwrap();
+                        objects.insert(path, body);
+                        ("201 Created", vec![])
+                    }
+                    _ => match objects.get(&path) {
+                        Some(body) => ("200 OK", body.clone()),
+                        None => ("404 Not Found", vec![]),
+                    },
+                };
+                write!(
+                    stream,
+                    "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    status,
+                    body.len()
+                )
+                .unwrap();
+                stream.write_all(&body).unwrap();
+            }
+        });
+
+        format!("http://{}/cache/", addr)
+    }
+
+    fn cache(url: String) -> RemoteCache {
+        RemoteCache::new(&RemoteCacheOptions {
+            url: Some(url),
+            auth_header: Some("Authorization: Bearer t0ken".to_string()),
+            read_only: false,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn stores_and_fetches_artifacts() {
+        let url = serve(Arc::new(Mutex::new(HashMap::new())));
+        let cache = cache(url);
+        let wasm = b"\0asm\x01\0\0\0".to_vec();
+
+        assert_eq!(None, cache.fetch("abc").unwrap());
+        cache.store("abc", &wasm).unwrap();
+        assert_eq!(Some(wasm), cache.fetch("abc").unwrap());
+    }
+
+    #[test]
+    fn misses_artifacts_of_other_optimizer_versions() {
+        let url = serve(Arc::new(Mutex::new(HashMap::new())));
+        let dir = tempfile::tempdir().unwrap();
+        let (wasm_path, output_path) = (dir.path().join("a.wasm"), dir.path().join("a-opt.wasm"));
+        fs::write(&wasm_path, b"\0asm\x01\0\0\0intermediate").unwrap();
+        fs::write(&output_path, b"\0asm\x01\0\0\0").unwrap();
+
+        let mut cache = cache(url);
+        cache.fingerprint = optimizer_fingerprint("0.10.0");
+        cache.upload(&wasm_path, &output_path).unwrap();
+        assert!(cache.restore(&wasm_path, &output_path).unwrap());
+
+        cache.fingerprint = optimizer_fingerprint("0.11.0");
+        assert!(!cache.restore(&wasm_path, &output_path).unwrap());
+    }
+
+    #[test]
+    fn rejects_artifacts_not_matching_recorded_digest() {
+        let objects = Arc::new(Mutex::new(HashMap::new()));
+        let url = serve(objects.clone());
+        let cache = cache(url);
+
+        objects.lock().unwrap().extend([
+            ("/cache/abc.wasm".to_string(), b"tampered".to_vec()),
+            (
+                "/cache/abc.sha256".to_string(),
+                sha256_digest(b"original".as_slice()).unwrap().into_bytes(),
+            ),
+        ]);
+
+        assert!(cache.fetch("abc").is_err());
+    }
+}
@@ -0,0 +1,295 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Result};
+use cargo::{core::Workspace, Config};
+use serde::Deserialize;
+
+use crate::{
+    compression::DEFAULT_MAX_UPLOAD_SIZE,
+    ext::TakeExt,
+    find_manifest,
+    inspection::{inspect_module, Inspection},
+    METADATA_KEY,
+};
+
+const CHAINS_KEY: &str = "chains";
+/// Interface version of contracts built for wasmvm 1.0 & later.
+const INTERFACE_VERSION: &str = "8";
+/// Capabilities which every wasmvm 1.x & 2.x chain is expected to support.
+const BASE_CAPABILITIES: [&str; 3] = ["iterator", "staking", "stargate"];
+
+/// A target chain: what it supports, and how large a contract it accepts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChainProfile {
+    pub name: String,
+    /// wasmvm version, e.g. `1.5`.
+    pub wasmvm: String,
+    pub capabilities: BTreeSet<String>,
+    /// WASM features beyond the MVP, named like by `inspect`.
+    pub features: BTreeSet<String>,
+    pub max_code_size: u64,
+}
+
+/// A chain profile defined in `[workspace.metadata.cw-optimizoor.chains.<name>]`.
+/// Unset fields are taken from the profile it extends.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct ChainProfileConfig {
+    extends: Option<String>,
+    wasmvm: Option<String>,
+    capabilities: Option<Vec<String>>,
+    features: Option<Vec<String>>,
+    max_code_size: Option<u64>,
+}
+
+/// Returns the built-in profiles, one per wasmvm release line, e.g. `wasmvm-1.5`.
+/// They're a baseline: chains may disable capabilities like `stargate`, or accept smaller contracts.
+pub fn builtin_profiles() -> Vec<ChainProfile> {
+    let releases: [(&str, &[&str], &[&str]); 9] = [
+        ("1.0", &[], &["sign_extension"]),
+        ("1.1", &["cosmwasm_1_1"], &["sign_extension"]),
+        ("1.2", &["cosmwasm_1_2"], &["sign_extension"]),
+        ("1.3", &["cosmwasm_1_3"], &["sign_extension"]),
+        ("1.4", &["cosmwasm_1_4"], &["sign_extension", "floats"]),
+        ("1.5", &[], &["sign_extension", "floats"]),
+        ("2.0", &["cosmwasm_2_0"], &["sign_extension", "floats"]),
+        ("2.1", &["cosmwasm_2_1"], &["sign_extension", "floats"]),
+        ("2.2", &["cosmwasm_2_2"], &["sign_extension", "floats"]),
+    ];
+
+    // each release supports the capabilities of the previous ones
+    let mut capabilities = BASE_CAPABILITIES
+        .iter()
+        .map(|c| c.to_string())
+        .collect::<BTreeSet<_>>();
+    releases
+        .iter()
+        .map(|(wasmvm, added, features)| {
+            capabilities.extend(added.iter().map(|c| c.to_string()));
+            ChainProfile {
+                name: format!("wasmvm-{}", wasmvm),
+                wasmvm: wasmvm.to_string(),
+                capabilities: capabilities.clone(),
+                features: features.iter().map(|f| f.to_string()).collect(),
+                max_code_size: DEFAULT_MAX_UPLOAD_SIZE,
+            }
+        })
+        .collect()
+}
+
+/// Finds the chain profile by name, among the ones defined in the workspace metadata & the built-in ones.
+pub fn find_profile(ws: &Workspace, name: &str) -> Result<ChainProfile> {
+    resolve_profile(&configured_profiles(ws.custom_metadata())?, name, 0)
+}
+
+/// Finds the chain profile by name in the workspace, or among the built-in ones if there's no workspace.
+pub fn find_workspace_profile<P: AsRef<Path>>(
+    workspace_path: P,
+    name: &str,
+) -> Result<ChainProfile> {
+    match find_manifest(workspace_path) {
+        Ok(manifest_path) => {
+            let cfg = Config::default()?;
+            let ws = Workspace::new(manifest_path.as_path(), &cfg)?;
+            find_profile(&ws, name)
+        }
+        Err(_) => resolve_profile(&BTreeMap::new(), name, 0),
+    }
+}
+
+fn configured_profiles(
+    metadata: Option<&toml::Value>,
+) -> Result<BTreeMap<String, ChainProfileConfig>> {
+    metadata
+        .and_then(|metadata| metadata.get(METADATA_KEY))
+        .and_then(|metadata| metadata.get(CHAINS_KEY))
+        .map(|chains| {
+            chains
+                .clone()
+                .try_into()
+                .map_err(|e| anyhow!("Invalid {}.{}: {}", METADATA_KEY, CHAINS_KEY, e))
+        })
+        .transpose()
+        .map(Option::unwrap_or_default)
+}
+
+fn resolve_profile(
+    configured: &BTreeMap<String, ChainProfileConfig>,
+    name: &str,
+    depth: usize,
+) -> Result<ChainProfile> {
+    if depth > configured.len() {
+        return Err(anyhow!("Chain profile {} extends itself", name));
+    }
+
+    let Some(config) = configured.get(name) else {
+        return builtin_profiles()
+            .into_iter()
+            .find(|profile| profile.name == name)
+            .ok_or_else(|| {
+                let names = configured
+                    .keys()
+                    .cloned()
+                    .chain(builtin_profiles().into_iter().map(|p| p.name))
+                    .collect::<Vec<_>>();
+                anyhow!(
+                    "Unknown chain profile {}, expected one of: {}",
+                    name,
+                    names.join(", ")
+                )
+            });
+    };
+
+    let base = match &config.extends {
+        Some(extends) => Some(resolve_profile(configured, extends, depth + 1)?),
+        None => None,
+    };
+    let field = |field: &str| anyhow!("Chain profile {} is missing {}", name, field);
+
+    Ok(ChainProfile {
+        name: name.to_string(),
+        wasmvm: match (&config.wasmvm, &base) {
+            (Some(wasmvm), _) => wasmvm.clone(),
+            (None, Some(base)) => base.wasmvm.clone(),
+            (None, None) => return Err(field("wasmvm")),
+        },
+        capabilities: match (&config.capabilities, &base) {
+            (Some(capabilities), _) => capabilities.iter().cloned().collect(),
+            (None, Some(base)) => base.capabilities.clone(),
+            (None, None) => return Err(field("capabilities")),
+        },
+        features: match (&config.features, &base) {
+            (Some(features), _) => features.iter().cloned().collect(),
+            (None, Some(base)) => base.features.clone(),
+            (None, None) => BTreeSet::new(),
+        },
+        max_code_size: config
+            .max_code_size
+            .or(base.map(|base| base.max_code_size))
+            .unwrap_or(DEFAULT_MAX_UPLOAD_SIZE),
+    })
+}
+
+/// Lists each incompatibility of the inspected artifact with the chain profile.
+pub fn incompatibilities(profile: &ChainProfile, inspection: &Inspection) -> Vec<String> {
+    let mut incompatibilities = vec![];
+    for capability in &inspection.capabilities {
+        if !profile.capabilities.contains(capability) {
+            incompatibilities.push(format!(
+                "requires the unsupported capability {}",
+                capability
+            ));
+        }
+    }
+    for feature in &inspection.features {
+        if !profile.features.contains(feature) {
+            incompatibilities.push(format!("uses the unsupported WASM feature {}", feature));
+        }
+    }
+    if inspection.size > profile.max_code_size {
+        incompatibilities.push(format!(
+            "is {} bytes, exceeding the max code size of {} bytes",
+            inspection.size, profile.max_code_size
+        ));
+    }
+    match inspection.interface_version.as_deref() {
+        Some(INTERFACE_VERSION) => {}
+        Some(version) => incompatibilities.push(format!(
+            "has interface version {}, but wasmvm {} expects {}",
+            version, profile.wasmvm, INTERFACE_VERSION
+        )),
+        None => incompatibilities.push(String::from("doesn't export an interface version")),
+    }
+
+    incompatibilities
+}
+
+/// Validates the artifacts against the chain profile, and fails if any of them is incompatible.
+pub fn check_compatibility(ws: &Workspace, chain: &str, wasm_paths: &[PathBuf]) -> Result<()> {
+    let profile = find_profile(ws, chain)?;
+
+    println!(
+        "⛓️  Checking compatibility with {} (wasmvm {})",
+        profile.name, profile.wasmvm
+    );
+    let mut compatible = true;
+    for wasm_path in wasm_paths {
+        let artifact = wasm_path.rtake(1).display().to_string();
+        let incompatibilities =
+            incompatibilities(&profile, &inspect_module(&fs::read(wasm_path)?)?);
+        if incompatibilities.is_empty() {
+            println!("    ...✅ {} is compatible.", artifact);
+        } else {
+            compatible = false;
+            println!("    ...❌ {} is incompatible:", artifact);
+            incompatibilities
+                .iter()
+                .for_each(|incompatibility| println!("         {}", incompatibility));
+        }
+    }
+
+    if !compatible {
+        return Err(anyhow!("Artifacts are incompatible with {}", profile.name));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        chain::{builtin_profiles, configured_profiles, incompatibilities, resolve_profile},
+        inspection::Inspection,
+    };
+
+    #[test]
+    fn resolves_configured_profiles() {
+        let metadata = toml::from_str::<toml::Value>(
+            r#"
+            [cw-optimizoor.chains.mychain]
+            extends = "wasmvm-1.5"
+            capabilities = ["iterator", "staking"]
+            max-code-size = 614400
+            "#,
+        )
+        .unwrap();
+        let configured = configured_profiles(Some(&metadata)).unwrap();
+
+        let profile = resolve_profile(&configured, "mychain", 0).unwrap();
+        assert_eq!("1.5", profile.wasmvm);
+        assert_eq!(
+            vec!["iterator", "staking"],
+            profile.capabilities.iter().collect::<Vec<_>>()
+        );
+        assert!(profile.features.contains("floats"));
+        assert_eq!(614_400, profile.max_code_size);
+        assert!(resolve_profile(&configured, "unknown", 0).is_err());
+    }
+
+    #[test]
+    fn lists_incompatibilities() {
+        let profile = builtin_profiles()
+            .into_iter()
+            .find(|p| p.name == "wasmvm-1.3")
+            .unwrap();
+        let inspection = Inspection {
+            size: 1_000,
+            interface_version: Some("8".to_string()),
+            capabilities: vec!["stargate".to_string(), "cosmwasm_2_0".to_string()],
+            features: vec!["sign_extension".to_string(), "floats".to_string()],
+            ..Inspection::default()
+        };
+
+        assert_eq!(
+            vec![
+                "requires the unsupported capability cosmwasm_2_0",
+                "uses the unsupported WASM feature floats"
+            ],
+            incompatibilities(&profile, &inspection)
+        );
+    }
+}
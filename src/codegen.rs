@@ -0,0 +1,220 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Component, Path, PathBuf},
+};
+
+use anyhow::{anyhow, Context, Result};
+use path_absolutize::Absolutize;
+
+use crate::hashing::{read_checksums_manifest, ArtifactChecksums, HashAlgorithm};
+
+/// Default filename of the generated module, in the artifacts dir.
+pub const RUST_MODULE: &str = "artifacts.rs";
+const CHECKSUMS_JSON: &str = "checksums.json";
+/// Strict & reserved keywords of the 2021 edition, which can't be module names as they are.
+const KEYWORDS: [&str; 51] = [
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "crate",
+    "do", "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "if", "impl", "in",
+    "let", "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref",
+    "return", "self", "Self", "static", "struct", "super", "trait", "true", "try", "type",
+    "typeof", "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
+];
+
+/// Generates a Rust module with a submodule per contract in `checksums.json`, named like its package in snake case,
+/// e.g. `cw20_base` for `cw20-base`. Each exposes the artifact's bytes, checksum & package version, so that tests
+/// can load contracts by name, and fail to compile if an artifact is missing.
+/// The artifacts are included by their filename, which has the arch suffix of the host, so the module has to be
+/// generated on each machine rather than committed.
+pub fn write_rust_module(output_dir: &Path, output_file: &Path) -> Result<()> {
+    let manifest = read_checksums_manifest(&output_dir.join(CHECKSUMS_JSON))?;
+    let module_dir = output_file
+        .absolutize()?
+        .parent()
+        .map(Path::to_path_buf)
+        .ok_or_else(|| anyhow!("Invalid module path: {}", output_file.display()))?;
+    fs::create_dir_all(&module_dir)?;
+
+    let artifacts = manifest
+        .artifacts
+        .iter()
+        .map(|artifact| {
+            let wasm_path = output_dir.absolutize()?.join(&artifact.name);
+            Ok((artifact, relative_path(&module_dir, &wasm_path)))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    fs::write(output_file, render_module(&artifacts)?)
+        .context(format!("Failed write into {}", output_file.display()))?;
+    println!(
+        "    ...📝 Saved the Rust module to {}",
+        output_file.display()
+    );
+
+    Ok(())
+}
+
+/// Renders the module from the artifacts, along with their paths relative to the module.
+/// Fails if the names of multiple packages map to the same module name, e.g. `foo-bar` & `foo_bar`.
+pub fn render_module(artifacts: &[(&ArtifactChecksums, PathBuf)]) -> Result<String> {
+    let mut module = String::from(
+        "// @generated by cw-optimizoor, do not edit.\n\
+        // Optimized artifacts of the contracts, by contract name.\n",
+    );
+    let mut packages = BTreeMap::new();
+    for (artifact, relative_path) in artifacts {
+        let checksum = artifact
+            .digests
+            .get(&HashAlgorithm::Sha256)
+            .ok_or_else(|| anyhow!("{} has no SHA-256 digest", artifact.name))?;
+        let name = module_name(&artifact.package);
+        if let Some(other) = packages.insert(name.clone(), &artifact.package) {
+            return Err(anyhow!(
+                "Packages {} & {} both map to the module name {}",
+                other,
+                artifact.package,
+                name
+            ));
+        }
+        module.push_str(&format!(
+            "\n/// `{package}` {version}\n\
+            pub mod {name} {{\n    \
+                /// Package name of the contract.\n    \
+                pub const PACKAGE: &str = {package:?};\n    \
+                /// Package version of the contract.\n    \
+                pub const VERSION: &str = {version:?};\n    \
+                /// Hex encoded SHA-256 checksum of the optimized artifact.\n    \
+                pub const CHECKSUM: &str = {checksum:?};\n    \
+                /// The optimized artifact.\n    \
+                pub const WASM: &[u8] = include_bytes!({relative_path:?});\n\
+            }}\n",
+            name = name,
+            package = artifact.package,
+            version = artifact.version,
+            checksum = checksum,
+            relative_path = relative_path.display().to_string(),
+        ));
+    }
+
+    Ok(module)
+}
+
+/// Returns the package name as a module name, e.g. `cw20_base` for `cw20-base`, or `type_` for `type`.
+fn module_name(package: &str) -> String {
+    let name = package.replace('-', "_");
+    match name.starts_with(|c: char| c.is_ascii_digit()) {
+        true => format!("_{}", name),
+        false if KEYWORDS.contains(&name.as_str()) => format!("{}_", name),
+        false => name,
+    }
+}
+
+/// Returns the path relative to the dir, both being absolute.
+pub fn relative_path(dir: &Path, path: &Path) -> PathBuf {
+    let dir_components = dir.components().collect::<Vec<_>>();
+    let path_components = path.components().collect::<Vec<_>>();
+    let common = dir_components
+        .iter()
+        .zip(&path_components)
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    dir_components[common..]
+        .iter()
+        .map(|_| Component::ParentDir)
+        .chain(path_components[common..].iter().cloned())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::BTreeMap,
+        path::{Path, PathBuf},
+    };
+
+    use crate::{
+        codegen::{module_name, relative_path, render_module},
+        hashing::{ArtifactChecksums, HashAlgorithm},
+    };
+
+    #[test]
+    fn renders_rust_modules() {
+        let artifact = ArtifactChecksums {
+            name: "cw20_base-aarch64.wasm".to_string(),
+            package: "cw20-base".to_string(),
+            version: "1.0.0".to_string(),
+            size: 100,
+            gzip_size: 50,
+            digests: BTreeMap::from([(HashAlgorithm::Sha256, "aa".to_string())]),
+            gzip: None,
+            schemas: BTreeMap::new(),
+        };
+        let artifacts = [(&artifact, PathBuf::from("cw20_base-aarch64.wasm"))];
+
+        assert_eq!(
+            "// @generated by cw-optimizoor, do not edit.\n\
+            // Optimized artifacts of the contracts, by contract name.\n\
+            \n\
+            /// `cw20-base` 1.0.0\n\
+            pub mod cw20_base {\n    \
+                /// Package name of the contract.\n    \
+                pub const PACKAGE: &str = \"cw20-base\";\n    \
+                /// Package version of the contract.\n    \
+                pub const VERSION: &str = \"1.0.0\";\n    \
+                /// Hex encoded SHA-256 checksum of the optimized artifact.\n    \
+                pub const CHECKSUM: &str = \"aa\";\n    \
+                /// The optimized artifact.\n    \
+                pub const WASM: &[u8] = include_bytes!(\"cw20_base-aarch64.wasm\");\n\
+            }\n",
+            render_module(&artifacts).unwrap()
+        );
+    }
+
+    #[test]
+    fn rejects_conflicting_module_names() {
+        let artifact = |package: &str| ArtifactChecksums {
+            name: format!("{}.wasm", package.replace('-', "_")),
+            package: package.to_string(),
+            version: "1.0.0".to_string(),
+            size: 100,
+            gzip_size: 50,
+            digests: BTreeMap::from([(HashAlgorithm::Sha256, "aa".to_string())]),
+            gzip: None,
+            schemas: BTreeMap::new(),
+        };
+        let (a, b) = (artifact("foo-bar"), artifact("foo_bar"));
+        let artifacts = [
+            (&a, PathBuf::from("foo_bar.wasm")),
+            (&b, PathBuf::from("foo_bar.wasm")),
+        ];
+
+        assert!(render_module(&artifacts[..1]).is_ok());
+        assert!(render_module(&artifacts).is_err());
+    }
+
+    #[test]
+    fn escapes_module_names() {
+        assert_eq!("cw20_base", module_name("cw20-base"));
+        assert_eq!("_1inch", module_name("1inch"));
+        assert_eq!("type_", module_name("type"));
+        assert_eq!("self_", module_name("self"));
+    }
+
+    #[test]
+    fn relativizes_paths() {
+        assert_eq!(
+            Path::new("../../artifacts/a.wasm"),
+            relative_path(
+                Path::new("/ws/tests/src"),
+                Path::new("/ws/artifacts/a.wasm")
+            )
+        );
+        assert_eq!(
+            Path::new("a.wasm"),
+            relative_path(
+                Path::new("/ws/artifacts"),
+                Path::new("/ws/artifacts/a.wasm")
+            )
+        );
+    }
+}
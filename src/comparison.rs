@@ -0,0 +1,360 @@
+use std::{collections::BTreeMap, fs, path::Path};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    chain::{find_workspace_profile, incompatibilities},
+    compression::DEFAULT_MAX_UPLOAD_SIZE,
+    inspection::{inspect_module, Inspection},
+    wasm::{function_sizes, module_items},
+};
+
+const MIGRATE: &str = "migrate";
+const NAME_SECTION: &str = "name";
+
+/// Differences between the interfaces of two artifacts, e.g. of a contract before & after a migration.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ArtifactDiff {
+    pub size: SizeChange,
+    pub old_interface_version: Option<String>,
+    pub new_interface_version: Option<String>,
+    pub entry_points: ListChange,
+    pub capabilities: ListChange,
+    pub features: ListChange,
+    pub exports: ListChange,
+    pub imports: ListChange,
+    /// Sections whose size changed, or which only exist in one of the artifacts.
+    pub sections: Vec<SizeChange>,
+    /// Functions whose size changed, if both artifacts have a names section.
+    pub functions: Option<Vec<SizeChange>>,
+    /// Changes which break migrating to the new artifact, or uploading it.
+    pub breaking: Vec<String>,
+    /// Changes which may break on chains that don't support them.
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ListChange {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SizeChange {
+    pub name: String,
+    pub old: Option<u64>,
+    pub new: Option<u64>,
+}
+
+impl ListChange {
+    fn new(old: &[String], new: &[String]) -> ListChange {
+        ListChange {
+            added: new.iter().filter(|i| !old.contains(i)).cloned().collect(),
+            removed: old.iter().filter(|i| !new.contains(i)).cloned().collect(),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+}
+
+impl SizeChange {
+    fn delta(&self) -> i64 {
+        self.new.unwrap_or_default() as i64 - self.old.unwrap_or_default() as i64
+    }
+}
+
+/// Compares the two artifacts, optionally checking the new one against the chain profile,
+/// and prints the differences either as a report or as JSON.
+pub fn diff<P: AsRef<Path>>(
+    old_path: &Path,
+    new_path: &Path,
+    workspace_path: P,
+    chain: Option<&str>,
+    json: bool,
+) -> Result<()> {
+    let old = fs::read(old_path).context(format!("Failed read from {}", old_path.display()))?;
+    let new = fs::read(new_path).context(format!("Failed read from {}", new_path.display()))?;
+
+    let mut artifact_diff = diff_modules(&old, &new)?;
+    if let Some(chain) = chain {
+        let profile = find_workspace_profile(workspace_path, chain)?;
+        artifact_diff.breaking.extend(
+            incompatibilities(&profile, &inspect_module(&new)?)
+                .into_iter()
+                .map(|incompatibility| format!("on {}: {}", profile.name, incompatibility)),
+        );
+    }
+
+    match json {
+        true => println!("{}", serde_json::to_string_pretty(&artifact_diff)?),
+        false => print_diff(old_path, new_path, &artifact_diff),
+    }
+
+    Ok(())
+}
+
+/// Compares the interfaces, sections & named functions of the two WASM modules.
+pub fn diff_modules(old: &[u8], new: &[u8]) -> Result<ArtifactDiff> {
+    let old_inspection = inspect_module(old)?;
+    let new_inspection = inspect_module(new)?;
+
+    let exports = |inspection: &Inspection| {
+        inspection
+            .exports
+            .iter()
+            .map(|e| format!("{} ({})", e.name, e.kind))
+            .collect::<Vec<_>>()
+    };
+    let imports = |inspection: &Inspection| {
+        inspection
+            .imports
+            .iter()
+            .map(|i| format!("{}.{} ({})", i.module, i.name, i.kind))
+            .collect::<Vec<_>>()
+    };
+    let sections = |wasm: &[u8]| -> Result<Vec<(String, u64)>> {
+        Ok(module_items(wasm)?
+            .sections
+            .into_iter()
+            .map(|section| (section.name, section.size as u64))
+            .collect())
+    };
+    let has_names = |inspection: &Inspection| {
+        inspection
+            .custom_sections
+            .iter()
+            .any(|section| section.name == NAME_SECTION)
+    };
+    let functions = |wasm: &[u8]| -> Result<Vec<(String, u64)>> {
+        Ok(function_sizes(wasm)?
+            .into_iter()
+            .map(|(name, size)| (name, size as u64))
+            .collect())
+    };
+
+    let mut artifact_diff = ArtifactDiff {
+        size: SizeChange {
+            name: String::from("total"),
+            old: Some(old_inspection.size),
+            new: Some(new_inspection.size),
+        },
+        old_interface_version: old_inspection.interface_version.clone(),
+        new_interface_version: new_inspection.interface_version.clone(),
+        entry_points: ListChange::new(&old_inspection.entry_points, &new_inspection.entry_points),
+        capabilities: ListChange::new(&old_inspection.capabilities, &new_inspection.capabilities),
+        features: ListChange::new(&old_inspection.features, &new_inspection.features),
+        exports: ListChange::new(&exports(&old_inspection), &exports(&new_inspection)),
+        imports: ListChange::new(&imports(&old_inspection), &imports(&new_inspection)),
+        sections: size_changes(sections(old)?, sections(new)?),
+        functions: match has_names(&old_inspection) && has_names(&new_inspection) {
+            true => Some(size_changes(functions(old)?, functions(new)?)),
+            false => None,
+        },
+        ..ArtifactDiff::default()
+    };
+    flag_changes(&mut artifact_diff);
+
+    Ok(artifact_diff)
+}
+
+/// Pairs up the sizes by name, and keeps the ones which changed.
+fn size_changes(old: Vec<(String, u64)>, new: Vec<(String, u64)>) -> Vec<SizeChange> {
+    let mut changes = BTreeMap::<String, SizeChange>::new();
+    for (name, size) in old {
+        let change = changes.entry(name.clone()).or_insert_with(|| SizeChange {
+            name,
+            ..SizeChange::default()
+        });
+        change.old = Some(change.old.unwrap_or_default() + size);
+    }
+    for (name, size) in new {
+        let change = changes.entry(name.clone()).or_insert_with(|| SizeChange {
+            name,
+            ..SizeChange::default()
+        });
+        change.new = Some(change.new.unwrap_or_default() + size);
+    }
+
+    changes
+        .into_values()
+        .filter(|change| change.old != change.new)
+        .collect()
+}
+
+/// Flags the changes which break migrating to, or uploading the new artifact.
+fn flag_changes(artifact_diff: &mut ArtifactDiff) {
+    for entry_point in &artifact_diff.entry_points.removed {
+        artifact_diff.breaking.push(match entry_point.as_str() {
+            MIGRATE => String::from(
+                "removes the migrate entry point, so contracts can't be migrated to it",
+            ),
+            _ => format!("removes the {} entry point", entry_point),
+        });
+    }
+    if artifact_diff.old_interface_version != artifact_diff.new_interface_version {
+        artifact_diff.breaking.push(format!(
+            "changes the interface version from {} to {}",
+            artifact_diff
+                .old_interface_version
+                .as_deref()
+                .unwrap_or("unknown"),
+            artifact_diff
+                .new_interface_version
+                .as_deref()
+                .unwrap_or("unknown")
+        ));
+    }
+    if let Some(size) = artifact_diff.size.new {
+        if size > DEFAULT_MAX_UPLOAD_SIZE {
+            artifact_diff.breaking.push(format!(
+                "is {} bytes, exceeding the usual max code size of {} bytes",
+                size, DEFAULT_MAX_UPLOAD_SIZE
+            ));
+        }
+    }
+
+    for capability in &artifact_diff.capabilities.added {
+        artifact_diff
+            .warnings
+            .push(format!("requires the new capability {}", capability));
+    }
+    for feature in &artifact_diff.features.added {
+        artifact_diff
+            .warnings
+            .push(format!("uses the new WASM feature {}", feature));
+    }
+    for import in &artifact_diff.imports.added {
+        artifact_diff
+            .warnings
+            .push(format!("imports the new host function {}", import));
+    }
+}
+
+fn print_diff(old_path: &Path, new_path: &Path, artifact_diff: &ArtifactDiff) {
+    let version = |version: &Option<String>| version.clone().unwrap_or_else(|| "unknown".into());
+    let print_list = |title: &str, change: &ListChange| {
+        if change.is_empty() {
+            return;
+        }
+        println!("    {}:", title);
+        change.added.iter().for_each(|i| println!("    ...+ {}", i));
+        change
+            .removed
+            .iter()
+            .for_each(|i| println!("    ...- {}", i));
+    };
+    let print_sizes = |title: &str, changes: &[SizeChange]| {
+        if changes.is_empty() {
+            return;
+        }
+        println!("    {}:", title);
+        for change in changes {
+            let size = |size: Option<u64>| size.map_or_else(|| "-".into(), |s| s.to_string());
+            println!(
+                "    ...{:<40} {:>10} {:>10} {:>+10}",
+                change.name,
+                size(change.old),
+                size(change.new),
+                change.delta()
+            );
+        }
+    };
+
+    println!("🔀  {} -> {}", old_path.display(), new_path.display());
+    println!(
+        "    Size: {} -> {} bytes ({:+})",
+        artifact_diff.size.old.unwrap_or_default(),
+        artifact_diff.size.new.unwrap_or_default(),
+        artifact_diff.size.delta()
+    );
+    println!(
+        "    Interface version: {} -> {}",
+        version(&artifact_diff.old_interface_version),
+        version(&artifact_diff.new_interface_version)
+    );
+    print_list("Entry points", &artifact_diff.entry_points);
+    print_list("Capabilities", &artifact_diff.capabilities);
+    print_list("Features", &artifact_diff.features);
+    print_list("Exports", &artifact_diff.exports);
+    print_list("Imports", &artifact_diff.imports);
+    print_sizes("Sections", &artifact_diff.sections);
+    if let Some(functions) = &artifact_diff.functions {
+        print_sizes("Functions", functions);
+    }
+
+    for breaking in &artifact_diff.breaking {
+        println!("    ...❌ {}", breaking);
+    }
+    for warning in &artifact_diff.warnings {
+        println!("    ...⚠️  {}", warning);
+    }
+    if artifact_diff.breaking.is_empty() && artifact_diff.warnings.is_empty() {
+        println!("    ...✅ no breaking changes.");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::comparison::diff_modules;
+
+    fn name(name: &str) -> Vec<u8> {
+        [&[name.len() as u8], name.as_bytes()].concat()
+    }
+
+    fn section(id: u8, contents: &[u8]) -> Vec<u8> {
+        [&[id, contents.len() as u8], contents].concat()
+    }
+
+    /// A module exporting the given functions, which all have empty bodies.
+    fn module(exports: &[&str]) -> Vec<u8> {
+        let count = exports.len() as u8;
+        [
+            vec![0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00],
+            // (type (func))
+            section(0x01, &[0x01, 0x60, 0x00, 0x00]),
+            section(0x03, &[vec![count], vec![0x00; count as usize]].concat()),
+            section(
+                0x07,
+                &[
+                    vec![count],
+                    exports
+                        .iter()
+                        .enumerate()
+                        .flat_map(|(i, e)| [name(e), vec![0x00, i as u8]].concat())
+                        .collect(),
+                ]
+                .concat(),
+            ),
+            section(
+                0x0a,
+                &[vec![count], [0x02, 0x00, 0x0b].repeat(count as usize)].concat(),
+            ),
+        ]
+        .concat()
+    }
+
+    #[test]
+    fn flags_breaking_changes() {
+        let old = module(&["interface_version_8", "instantiate", "migrate"]);
+        let new = module(&["interface_version_8", "instantiate", "requires_stargate"]);
+
+        let artifact_diff = diff_modules(&old, &new).unwrap();
+        assert_eq!(vec!["migrate"], artifact_diff.entry_points.removed);
+        assert_eq!(vec!["stargate"], artifact_diff.capabilities.added);
+        assert_eq!(
+            vec!["removes the migrate entry point, so contracts can't be migrated to it"],
+            artifact_diff.breaking
+        );
+        assert_eq!(
+            vec!["requires the new capability stargate"],
+            artifact_diff.warnings
+        );
+        assert_eq!(None, artifact_diff.functions);
+
+        let artifact_diff = diff_modules(&old, &old).unwrap();
+        assert!(artifact_diff.breaking.is_empty() && artifact_diff.sections.is_empty());
+    }
+}
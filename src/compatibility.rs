@@ -0,0 +1,570 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{find_artifacts_dir, git, schema::SCHEMA, ARTIFACTS};
+
+/// Messages whose schemas are compared.
+const MESSAGES: [&str; 4] = ["instantiate", "execute", "query", "migrate"];
+/// Key of the combined schema generated by `write_api!`.
+const IDL_VERSION: &str = "idl_version";
+const MSG_SUFFIX: &str = "_msg";
+const JSON: &str = "json";
+const NULL: &str = "null";
+
+/// Message schemas of a contract, by message.
+pub type MessageSchemas = BTreeMap<String, Value>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Compatibility {
+    Compatible,
+    Breaking,
+}
+
+impl fmt::Display for Compatibility {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Compatibility::Compatible => write!(f, "compatible"),
+            Compatibility::Breaking => write!(f, "breaking"),
+        }
+    }
+}
+
+/// A change of a message schema of a contract.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SchemaChange {
+    pub contract: String,
+    /// Path of the changed schema, e.g. `execute.transfer.amount`.
+    pub path: String,
+    pub change: String,
+    pub compatibility: Compatibility,
+}
+
+/// Compares the message schemas in the artifacts dir against the ones of a baseline, which is either a dir
+/// (an artifacts dir, or a schema dir of it) or a git ref at which the artifacts dir was committed.
+/// Fails if any change is breaking.
+pub fn schema_diff<P: AsRef<Path>>(workspace_path: P, baseline: &str, json: bool) -> Result<()> {
+    let artifacts_dir = find_artifacts_dir(workspace_path)?;
+    let current = read_schema_dir(&artifacts_dir.join(SCHEMA))?;
+
+    let baseline_path = PathBuf::from(baseline);
+    let baseline_schemas = if baseline_path.join(SCHEMA).is_dir() {
+        read_schema_dir(&baseline_path.join(SCHEMA))?
+    } else if baseline_path.is_dir() {
+        read_schema_dir(&baseline_path)?
+    } else {
+        let ws_root = artifacts_dir
+            .parent()
+            .ok_or_else(|| anyhow!("Invalid artifacts dir: {}", artifacts_dir.display()))?;
+        contract_schemas(git::files_at(
+            ws_root,
+            baseline,
+            &Path::new(ARTIFACTS).join(SCHEMA),
+        )?)?
+    };
+
+    let changes = compare_contracts(&baseline_schemas, &current);
+    match json {
+        true => println!("{}", serde_json::to_string_pretty(&changes)?),
+        false => {
+            println!("🧬  Schema changes since {}:", baseline);
+            print_changes(&changes);
+        }
+    }
+
+    let breaking = changes
+        .iter()
+        .filter(|c| c.compatibility == Compatibility::Breaking)
+        .count();
+    if breaking > 0 {
+        return Err(anyhow!("{} breaking schema change(s)", breaking));
+    }
+
+    Ok(())
+}
+
+/// Reads the message schemas of each contract from a schema dir, i.e. from `<dir>/<contract>/**/*.json`.
+pub fn read_schema_dir(dir: &Path) -> Result<BTreeMap<String, MessageSchemas>> {
+    fn walk(dir: &Path, base: &Path, files: &mut Vec<(PathBuf, Vec<u8>)>) -> Result<()> {
+        for entry in fs::read_dir(dir).context(format!("Failed read from {}", dir.display()))? {
+            let path = entry?.path();
+            match path.is_dir() {
+                true => walk(&path, base, files)?,
+                false => files.push((path.strip_prefix(base)?.to_path_buf(), fs::read(&path)?)),
+            }
+        }
+        Ok(())
+    }
+
+    let mut files = vec![];
+    walk(dir, dir, &mut files)?;
+    contract_schemas(files)
+}
+
+/// Groups the schema files by contract, i.e. by their first path component, and extracts their message schemas.
+fn contract_schemas(files: Vec<(PathBuf, Vec<u8>)>) -> Result<BTreeMap<String, MessageSchemas>> {
+    let mut contracts = BTreeMap::<String, Vec<(String, Value)>>::new();
+    for (path, contents) in files {
+        if path.extension().and_then(|ext| ext.to_str()) != Some(JSON) {
+            continue;
+        }
+        let Some(contract) = path.iter().next().filter(|_| path.iter().count() > 1) else {
+            continue;
+        };
+        let schema = serde_json::from_slice(&contents)
+            .context(format!("Invalid JSON schema {}", path.display()))?;
+        contracts
+            .entry(contract.to_string_lossy().to_string())
+            .or_default()
+            .push((path.display().to_string(), schema));
+    }
+
+    Ok(contracts
+        .into_iter()
+        .map(|(contract, files)| (contract, message_schemas(&files)))
+        .collect())
+}
+
+/// Extracts the message schemas from the schema files of a contract: either from the combined schema generated by
+/// `write_api!`, or from the individual message schemas, e.g. `raw/execute.json` or `execute_msg.json`.
+pub fn message_schemas(files: &[(String, Value)]) -> MessageSchemas {
+    let mut schemas = MessageSchemas::new();
+    for (name, schema) in files {
+        let stem = Path::new(name)
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let message = stem.trim_end_matches(MSG_SUFFIX);
+        if MESSAGES.contains(&message) {
+            schemas.insert(message.to_string(), schema.clone());
+        }
+    }
+    // the combined schema takes precedence
+    for (_, schema) in files.iter().filter(|(_, s)| s.get(IDL_VERSION).is_some()) {
+        for message in MESSAGES {
+            if let Some(msg) = schema.get(message).filter(|msg| !msg.is_null()) {
+                schemas.insert(message.to_string(), msg.clone());
+            }
+        }
+    }
+
+    schemas
+}
+
+/// Compares the message schemas of the contracts which exist in both.
+pub fn compare_contracts(
+    baseline: &BTreeMap<String, MessageSchemas>,
+    current: &BTreeMap<String, MessageSchemas>,
+) -> Vec<SchemaChange> {
+    let mut changes = vec![];
+    for (contract, old) in baseline {
+        let Some(new) = current.get(contract) else {
+            continue;
+        };
+        for (message, old_schema) in old {
+            match new.get(message) {
+                Some(new_schema) => {
+                    let mut comparison = Comparison {
+                        old_root: old_schema,
+                        new_root: new_schema,
+                        visited: BTreeSet::new(),
+                        changes: vec![],
+                    };
+                    comparison.compare(old_schema, new_schema, message);
+                    changes.extend(comparison.changes.into_iter().map(
+                        |(path, change, compatibility)| SchemaChange {
+                            contract: contract.clone(),
+                            path,
+                            change,
+                            compatibility,
+                        },
+                    ));
+                }
+                None => changes.push(SchemaChange {
+                    contract: contract.clone(),
+                    path: message.clone(),
+                    change: format!("removes the {} message", message),
+                    compatibility: Compatibility::Breaking,
+                }),
+            }
+        }
+        for message in new.keys().filter(|message| !old.contains_key(*message)) {
+            changes.push(SchemaChange {
+                contract: contract.clone(),
+                path: message.clone(),
+                change: format!("adds the {} message", message),
+                compatibility: Compatibility::Compatible,
+            });
+        }
+    }
+
+    changes
+}
+
+/// Prints the changes, breaking ones first.
+pub fn print_changes(changes: &[SchemaChange]) {
+    if changes.is_empty() {
+        println!("    ...✅ no changes.");
+    }
+    for compatibility in [Compatibility::Breaking, Compatibility::Compatible] {
+        for change in changes.iter().filter(|c| c.compatibility == compatibility) {
+            let icon = match compatibility {
+                Compatibility::Breaking => "❌",
+                Compatibility::Compatible => "✅",
+            };
+            println!(
+                "    ...{} {} {}: {}",
+                icon, change.contract, change.path, change.change
+            );
+        }
+    }
+}
+
+/// Compares the schemas of a message from the perspective of its senders, i.e. whether messages which were valid
+/// against the old schema remain valid against the new one.
+struct Comparison<'a> {
+    old_root: &'a Value,
+    new_root: &'a Value,
+    /// Pairs of compared definitions, since they may be recursive.
+    visited: BTreeSet<(String, String)>,
+    changes: Vec<(String, String, Compatibility)>,
+}
+
+impl<'a> Comparison<'a> {
+    fn compare(&mut self, old: &'a Value, new: &'a Value, path: &str) {
+        let (old, old_ref) = resolve(old, self.old_root);
+        let (new, new_ref) = resolve(new, self.new_root);
+        if let (Some(old_ref), Some(new_ref)) = (old_ref, new_ref) {
+            if !self.visited.insert((old_ref, new_ref)) {
+                return;
+            }
+        }
+
+        let (old, old_nullable) = nullable(old);
+        let (new, new_nullable) = nullable(new);
+        let (old, _) = resolve(old, self.old_root);
+        let (new, _) = resolve(new, self.new_root);
+        if old_nullable && !new_nullable {
+            self.breaking(path, String::from("is no longer nullable"));
+        }
+
+        match (variants(old), variants(new)) {
+            (Some(old_variants), Some(new_variants)) => {
+                for (name, old_variant) in &old_variants {
+                    match new_variants.get(name) {
+                        Some(new_variant) => {
+                            self.compare(old_variant, new_variant, &format!("{}.{}", path, name))
+                        }
+                        None => self.breaking(path, format!("removes the variant {}", name)),
+                    }
+                }
+                for name in new_variants
+                    .keys()
+                    .filter(|n| !old_variants.contains_key(*n))
+                {
+                    self.compatible(path, format!("adds the variant {}", name));
+                }
+                return;
+            }
+            (Some(_), None) | (None, Some(_)) => {
+                self.breaking(path, String::from("changes between an enum and a non-enum"));
+                return;
+            }
+            (None, None) => {}
+        }
+
+        if old.get("properties").is_some() || new.get("properties").is_some() {
+            self.compare_objects(old, new, path);
+            return;
+        }
+
+        let (old_types, new_types) = (types(old), types(new));
+        if !old_types.is_empty() && !new_types.is_empty() {
+            if !old_types.is_subset(&new_types) {
+                self.breaking(
+                    path,
+                    format!(
+                        "changes the type from {} to {}",
+                        join(&old_types),
+                        join(&new_types)
+                    ),
+                );
+                return;
+            } else if old_types != new_types {
+                self.compatible(path, format!("widens the type to {}", join(&new_types)));
+            }
+        }
+        if let (Some(old_items), Some(new_items)) = (old.get("items"), new.get("items")) {
+            self.compare(old_items, new_items, &format!("{}[]", path));
+        }
+    }
+
+    fn compare_objects(&mut self, old: &'a Value, new: &'a Value, path: &str) {
+        let (old_properties, new_properties) = (properties(old), properties(new));
+        let (old_required, new_required) = (required(old), required(new));
+
+        for (&field, &old_field) in &old_properties {
+            let field_path = format!("{}.{}", path, field);
+            let Some(&new_field) = new_properties.get(&field) else {
+                self.breaking(path, format!("removes the field {}", field));
+                continue;
+            };
+            match (old_required.contains(field), new_required.contains(field)) {
+                (false, true) => self.breaking(&field_path, String::from("becomes required")),
+                (true, false) => self.compatible(&field_path, String::from("becomes optional")),
+                _ => {}
+            }
+            self.compare(old_field, new_field, &field_path);
+        }
+        for field in new_properties
+            .keys()
+            .filter(|f| !old_properties.contains_key(*f))
+        {
+            match new_required.contains(*field) {
+                true => self.breaking(path, format!("adds the required field {}", field)),
+                false => self.compatible(path, format!("adds the optional field {}", field)),
+            }
+        }
+    }
+
+    fn breaking(&mut self, path: &str, change: String) {
+        self.changes
+            .push((path.to_string(), change, Compatibility::Breaking));
+    }
+
+    fn compatible(&mut self, path: &str, change: String) {
+        self.changes
+            .push((path.to_string(), change, Compatibility::Compatible));
+    }
+}
+
+/// Follows the `$ref`s within the root schema, and unwraps single element `allOf`s, which schemars emits for
+/// documented references. Returns the resolved schema along with the last followed reference.
+fn resolve<'a>(mut schema: &'a Value, root: &'a Value) -> (&'a Value, Option<String>) {
+    let mut reference = None;
+    // bounded, in case of references to references
+    for _ in 0..16 {
+        if let Some(r) = schema.get("$ref").and_then(Value::as_str) {
+            match root.pointer(r.trim_start_matches('#')) {
+                Some(resolved) => {
+                    reference = Some(r.to_string());
+                    schema = resolved;
+                }
+                None => break,
+            }
+        } else if let Some([inner]) = schema
+            .get("allOf")
+            .and_then(Value::as_array)
+            .map(Vec::as_slice)
+        {
+            schema = inner;
+        } else {
+            break;
+        }
+    }
+
+    (schema, reference)
+}
+
+/// Unwraps an optional schema, i.e. an `anyOf` of a schema & `null`, and returns whether it was nullable.
+fn nullable(schema: &Value) -> (&Value, bool) {
+    if let Some(any_of) = schema.get("anyOf").and_then(Value::as_array) {
+        let non_null = any_of
+            .iter()
+            .filter(|s| s.get("type").and_then(Value::as_str) != Some(NULL))
+            .collect::<Vec<_>>();
+        if let [inner] = non_null.as_slice() {
+            return (inner, non_null.len() < any_of.len());
+        }
+    }
+
+    (schema, types(schema).contains(NULL))
+}
+
+/// Returns the variants of an enum schema by name: the single property of each externally tagged variant,
+/// and the values of unit variants.
+fn variants(schema: &Value) -> Option<BTreeMap<String, &Value>> {
+    let one_of = schema.get("oneOf").and_then(Value::as_array);
+    let values = schema.get("enum").and_then(Value::as_array);
+    if one_of.is_none() && values.is_none() {
+        return None;
+    }
+
+    let mut variants = BTreeMap::new();
+    let unit_variant = |value: &Value| {
+        value
+            .as_str()
+            .map(String::from)
+            .unwrap_or(value.to_string())
+    };
+    for value in values.into_iter().flatten() {
+        variants.insert(unit_variant(value), &Value::Null);
+    }
+    for (i, variant) in one_of.into_iter().flatten().enumerate() {
+        if let Some(values) = variant.get("enum").and_then(Value::as_array) {
+            for value in values {
+                variants.insert(unit_variant(value), &Value::Null);
+            }
+        } else if let Some([(name, payload)]) = variant
+            .get("properties")
+            .and_then(Value::as_object)
+            .map(|properties| properties.iter().collect::<Vec<_>>())
+            .as_deref()
+        {
+            variants.insert(name.to_string(), *payload);
+        } else {
+            variants.insert(format!("#{}", i), variant);
+        }
+    }
+
+    Some(variants)
+}
+
+/// Returns the types of the schema, without `null`.
+fn types(schema: &Value) -> BTreeSet<String> {
+    match schema.get("type") {
+        Some(Value::String(ty)) => BTreeSet::from([ty.clone()]),
+        Some(Value::Array(types)) => types
+            .iter()
+            .filter_map(Value::as_str)
+            .map(String::from)
+            .collect(),
+        _ => BTreeSet::new(),
+    }
+    .into_iter()
+    .filter(|ty| ty != NULL)
+    .collect()
+}
+
+fn properties(schema: &Value) -> BTreeMap<&String, &Value> {
+    schema
+        .get("properties")
+        .and_then(Value::as_object)
+        .into_iter()
+        .flatten()
+        .collect()
+}
+
+fn required(schema: &Value) -> BTreeSet<String> {
+    schema
+        .get("required")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+        .map(String::from)
+        .collect()
+}
+
+fn join(types: &BTreeSet<String>) -> String {
+    types.iter().cloned().collect::<Vec<_>>().join(" | ")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use serde_json::json;
+
+    use crate::compatibility::{compare_contracts, message_schemas, Compatibility};
+
+    fn execute_msg(
+        transfer: serde_json::Value,
+        variants: &[serde_json::Value],
+    ) -> serde_json::Value {
+        let mut one_of = vec![json!({
+            "type": "object",
+            "required": ["transfer"],
+            "properties": { "transfer": { "$ref": "#/definitions/Transfer" } },
+            "additionalProperties": false
+        })];
+        one_of.extend(variants.iter().cloned());
+        json!({
+            "$schema": "http://json-schema.org/draft-07/schema#",
+            "title": "ExecuteMsg",
+            "oneOf": one_of,
+            "definitions": { "Transfer": transfer }
+        })
+    }
+
+    #[test]
+    fn classifies_schema_changes() {
+        let burn = json!({
+            "type": "object",
+            "required": ["burn"],
+            "properties": { "burn": { "type": "object" } }
+        });
+        let old = execute_msg(
+            json!({
+                "type": "object",
+                "required": ["recipient"],
+                "properties": {
+                    "recipient": { "type": "string" },
+                    "amount": { "type": ["string", "null"] },
+                    "memo": { "type": ["string", "null"] }
+                }
+            }),
+            &[burn],
+        );
+        let new = execute_msg(
+            json!({
+                "type": "object",
+                "required": ["recipient", "amount"],
+                "properties": {
+                    "recipient": { "type": "string" },
+                    "amount": { "type": ["string", "null"] },
+                    "note": { "type": ["string", "null"] }
+                }
+            }),
+            &[json!({ "type": "string", "enum": ["pause"] })],
+        );
+
+        let schemas = |execute| {
+            BTreeMap::from([(
+                "cw20".to_string(),
+                message_schemas(&[("cw20/raw/execute.json".to_string(), execute)]),
+            )])
+        };
+        let changes = compare_contracts(&schemas(old), &schemas(new));
+        assert_eq!(
+            vec![
+                (
+                    "execute",
+                    "removes the variant burn",
+                    Compatibility::Breaking
+                ),
+                (
+                    "execute.transfer.amount",
+                    "becomes required",
+                    Compatibility::Breaking
+                ),
+                (
+                    "execute.transfer",
+                    "removes the field memo",
+                    Compatibility::Breaking
+                ),
+                (
+                    "execute.transfer",
+                    "adds the optional field note",
+                    Compatibility::Compatible
+                ),
+                (
+                    "execute",
+                    "adds the variant pause",
+                    Compatibility::Compatible
+                ),
+            ],
+            changes
+                .iter()
+                .map(|c| (c.path.as_str(), c.change.as_str(), c.compatibility))
+                .collect::<Vec<_>>()
+        );
+    }
+}
@@ -1,47 +1,80 @@
 use std::sync::Arc;
-use std::{cell::RefCell, env, path::PathBuf, thread};
+use std::{
+    cell::RefCell,
+    collections::BTreeMap,
+    env,
+    path::{Path, PathBuf},
+    thread,
+};
 
 use anyhow::Result;
 use cargo::{
     core::{
-        compiler::{BuildConfig, CompileKind, CompileMode, CompileTarget, MessageFormat},
-        resolver::CliFeatures,
+        compiler::{
+            BuildConfig, CompileKind, CompileMode, CompileTarget, MessageFormat, RustcTargetData,
+        },
+        resolver::{CliFeatures, FeaturesFor, ForceAllTargets, HasDevUnits},
         Package, Workspace,
     },
     ops::{self, CompileFilter, CompileOptions},
-    util::interning::InternedString,
+    util::{interning::InternedString, Filesystem},
     Config,
 };
+use cargo_util::ProcessBuilder;
 use lazy_static::lazy_static;
 
-const RUSTFLAGS: &str = "RUSTFLAGS";
-const PROFILE_RELEASE: &str = "release";
-const TARGET_WASM32: &str = "wasm32-unknown-unknown";
+pub(crate) const CARGO_ENCODED_RUSTFLAGS: &str = "CARGO_ENCODED_RUSTFLAGS";
+const REMAP_PATH_PREFIX: &str = "--remap-path-prefix=";
+const WORKSPACE_PLACEHOLDER: &str = "/workspace";
+const CARGO_HOME_PLACEHOLDER: &str = "/cargo";
+const SYSROOT_PLACEHOLDER: &str = "/sysroot";
+pub(crate) const PROFILE_RELEASE: &str = "release";
+pub(crate) const TARGET_WASM32: &str = "wasm32-unknown-unknown";
 lazy_static! {
     static ref KIND_WASM32: CompileKind =
         CompileKind::Target(CompileTarget::new(TARGET_WASM32).expect("couldn't create target"));
 }
 
-/// Compiles the workspace packages and returns the paths to the created WASM artifacts.
-pub fn compile(cfg: &Config, ws: &Workspace, packages: ops::Packages) -> Result<Vec<PathBuf>> {
-    let wasm_paths = ops::compile(ws, &compile_opts(cfg, packages)?)?
+/// An intermediate WASM artifact created by the compilation.
+#[derive(Debug, Clone)]
+pub struct CompiledWasm {
+    pub package_name: String,
+    pub package_version: String,
+    /// Features activated for the package.
+    pub features: Vec<String>,
+    pub path: PathBuf,
+}
+
+/// Compiles the workspace packages and returns the created WASM artifacts.
+pub fn compile(cfg: &Config, ws: &Workspace, packages: ops::Packages) -> Result<Vec<CompiledWasm>> {
+    let wasms = ops::compile(ws, &compile_opts(cfg, packages)?)?
         .cdylibs
         .into_iter()
         .filter(|o| o.unit.kind.eq(&KIND_WASM32))
-        .map(|o| o.path)
-        .collect::<Vec<PathBuf>>();
+        .map(|o| CompiledWasm {
+            package_name: o.unit.pkg.name().to_string(),
+            package_version: o.unit.pkg.version().to_string(),
+            features: o.unit.features.iter().map(|f| f.to_string()).collect(),
+            path: o.path,
+        })
+        .collect::<Vec<CompiledWasm>>();
 
-    Ok(wasm_paths)
+    Ok(wasms)
 }
 
 /// Variant of [`compile()`](fn@compile) which compiles each package individually by using ephemeral workspaces.
-pub fn compile_ephemerally(cfg: &Config, packages: Vec<Package>) -> anyhow::Result<Vec<PathBuf>> {
+/// Each ephemeral workspace uses the package's own target dir, unless a target dir is passed in.
+pub fn compile_ephemerally(
+    cfg: &Config,
+    packages: Vec<Package>,
+    target_dir: Option<Filesystem>,
+) -> anyhow::Result<Vec<CompiledWasm>> {
     packages
         .into_iter()
         .map(|p| {
             (
                 p.package_id().name().to_string(),
-                Workspace::ephemeral(p, cfg, None, false),
+                Workspace::ephemeral(p, cfg, target_dir.clone(), false),
             )
         })
         .try_fold(vec![], |mut acc, (package, ws)| {
@@ -51,6 +84,57 @@ pub fn compile_ephemerally(cfg: &Config, packages: Vec<Package>) -> anyhow::Resu
         })
 }
 
+/// Resolves the features which would be activated when compiling the workspace packages together,
+/// and returns them by package name.
+pub fn resolve_features(
+    cfg: &Config,
+    ws: &Workspace,
+    packages: &[Package],
+) -> Result<BTreeMap<String, Vec<String>>> {
+    let names = packages
+        .iter()
+        .map(|p| p.name().to_string())
+        .collect::<Vec<_>>();
+    let opts = compile_opts(cfg, ops::Packages::Packages(names))?;
+    let requested_kinds = &opts.build_config.requested_kinds;
+    let mut target_data = RustcTargetData::new(ws, requested_kinds)?;
+    let resolve = ops::resolve_ws_with_opts(
+        ws,
+        &mut target_data,
+        requested_kinds,
+        &opts.cli_features,
+        &opts.spec.to_package_id_specs(ws)?,
+        HasDevUnits::No,
+        ForceAllTargets::No,
+    )?;
+
+    let features = packages
+        .iter()
+        .map(|p| {
+            let features = resolve
+                .resolved_features
+                .activated_features(p.package_id(), FeaturesFor::default())
+                .iter()
+                .map(|f| f.to_string())
+                .collect();
+            (p.name().to_string(), features)
+        })
+        .collect();
+
+    Ok(features)
+}
+
+/// Returns the path of the intermediate WASM artifact which compiling the package would create.
+pub fn intermediate_wasm_path(ws: &Workspace, package: &Package) -> Option<PathBuf> {
+    package.targets().iter().find(|t| t.is_lib()).map(|lib| {
+        ws.target_dir()
+            .into_path_unlocked()
+            .join(TARGET_WASM32)
+            .join(PROFILE_RELEASE)
+            .join(format!("{}.wasm", lib.crate_name()))
+    })
+}
+
 /// Sets up the high-level compilation options.
 pub fn compile_opts(config: &Config, spec: ops::Packages) -> Result<CompileOptions> {
     Ok(CompileOptions {
@@ -67,16 +151,108 @@ pub fn compile_opts(config: &Config, spec: ops::Packages) -> Result<CompileOptio
 }
 
 /// Creates the cargo config.
-pub fn config() -> Result<Config> {
-    // https://github.com/rust-lang/rust/issues/71757
-    // https://github.com/rust-lang/cargo/pull/8246
-    env::set_var(RUSTFLAGS, "-C strip=symbols");
+/// Unless disabled, the absolute host paths returned by [`host_path_prefixes()`](fn@host_path_prefixes)
+/// are remapped to stable placeholders, so that they don't end up in the artifacts.
+pub fn config(manifest_path: &Path, remap_path_prefix: bool) -> Result<Config> {
+    config_with(manifest_path, remap_path_prefix, true)
+}
+
+/// Variant of [`config()`](fn@config) which keeps the symbols, so that the code can be attributed to functions.
+pub fn unstripped_config(manifest_path: &Path, remap_path_prefix: bool) -> Result<Config> {
+    config_with(manifest_path, remap_path_prefix, false)
+}
+
+fn config_with(
+    manifest_path: &Path,
+    remap_path_prefix: bool,
+    strip_symbols: bool,
+) -> Result<Config> {
+    let mut rustflags = vec![];
+    if strip_symbols {
+        // https://github.com/rust-lang/rust/issues/71757
+        // https://github.com/rust-lang/cargo/pull/8246
+        rustflags.extend([String::from("-C"), String::from("strip=symbols")]);
+    }
+    if remap_path_prefix {
+        let prefixes = host_path_prefixes(&Config::default()?, manifest_path)?;
+        rustflags.extend(prefixes.iter().map(|(path, placeholder)| {
+            format!("{}{}={}", REMAP_PATH_PREFIX, path.display(), placeholder)
+        }));
+    }
+    // the encoded variant allows for spaces in the remapped paths
+    env::set_var(CARGO_ENCODED_RUSTFLAGS, rustflags.join("\x1f"));
 
     let cfg = Config::default()?;
 
     Ok(cfg)
 }
 
+/// Returns the absolute host paths which would otherwise end up in the artifacts, e.g. in panic messages,
+/// together with their placeholders: the workspace root, `CARGO_HOME` & the rustc sysroot.
+/// They're ordered from the least to the most specific, since rustc applies the last matching remapping.
+pub fn host_path_prefixes(
+    cfg: &Config,
+    manifest_path: &Path,
+) -> Result<Vec<(PathBuf, &'static str)>> {
+    let ws_root = Workspace::new(manifest_path, cfg)?.root().to_path_buf();
+    let cargo_home = cfg.home().as_path_unlocked().to_path_buf();
+    let sysroot = sysroot(cfg)?;
+
+    let mut prefixes = vec![
+        (ws_root, WORKSPACE_PLACEHOLDER),
+        (cargo_home, CARGO_HOME_PLACEHOLDER),
+        (sysroot, SYSROOT_PLACEHOLDER),
+    ];
+    prefixes.sort_by_key(|(path, _)| path.as_os_str().len());
+
+    Ok(prefixes)
+}
+
+/// Returns the rustflags set by [`config()`](fn@config).
+pub fn rustflags() -> Vec<String> {
+    env::var(CARGO_ENCODED_RUSTFLAGS)
+        .map(|flags| flags.split('\x1f').map(String::from).collect())
+        .unwrap_or_default()
+}
+
+/// Returns the rustflags set by [`config()`](fn@config) with the host side of the remapped paths redacted,
+/// e.g. `--remap-path-prefix=<workspace>=/workspace`, so that they can be recorded independently of the host.
+pub fn recorded_rustflags() -> Vec<String> {
+    redact_host_paths(&rustflags())
+}
+
+/// Replaces the host paths of the `--remap-path-prefix` flags with the names of their placeholders.
+pub fn redact_host_paths(rustflags: &[String]) -> Vec<String> {
+    rustflags
+        .iter()
+        .map(|flag| {
+            match flag
+                .strip_prefix(REMAP_PATH_PREFIX)
+                .and_then(|remapping| remapping.rsplit_once('='))
+            {
+                Some((_, placeholder)) => format!(
+                    "{}<{}>={}",
+                    REMAP_PATH_PREFIX,
+                    placeholder.trim_start_matches('/'),
+                    placeholder
+                ),
+                None => flag.clone(),
+            }
+        })
+        .collect()
+}
+
+/// Returns the sysroot of the rustc used by cargo.
+fn sysroot(cfg: &Config) -> Result<PathBuf> {
+    let rustc = cfg.load_global_rustc(None)?;
+    let output = ProcessBuilder::new(&rustc.path)
+        .arg("--print")
+        .arg("sysroot")
+        .exec_with_output()?;
+
+    Ok(PathBuf::from(String::from_utf8(output.stdout)?.trim()))
+}
+
 /// Creates the rustc build config.
 pub fn build_cfg(config: &Config) -> Result<BuildConfig> {
     let cfg = config.build_config()?;
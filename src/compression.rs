@@ -0,0 +1,92 @@
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use flate2::{Compression, GzBuilder};
+
+pub const GZ: &str = "gz";
+/// Default max size of an uploaded contract, i.e. wasmd's `MaxWasmSize`.
+pub const DEFAULT_MAX_UPLOAD_SIZE: u64 = 819_200;
+/// Operating system field of the gzip header for "unknown", so that the output doesn't depend on the host.
+const OS_UNKNOWN: u8 = 255;
+
+/// Compresses the data with gzip deterministically: without a filename, and with a zero modification time.
+pub fn gzip(data: &[u8]) -> Result<Vec<u8>> {
+    let mut encoder = GzBuilder::new()
+        .mtime(0)
+        .operating_system(OS_UNKNOWN)
+        .write(Vec::new(), Compression::best());
+    encoder.write_all(data)?;
+
+    Ok(encoder.finish()?)
+}
+
+/// Returns the size of the data gzipped like by [`gzip`], without keeping the compressed output.
+pub fn gzipped_size(data: &[u8]) -> Result<u64> {
+    let mut encoder = GzBuilder::new()
+        .mtime(0)
+        .operating_system(OS_UNKNOWN)
+        .write(ByteCounter::default(), Compression::best());
+    encoder.write_all(data)?;
+
+    Ok(encoder.finish()?.0)
+}
+
+/// Writer that only counts the bytes written into it.
+#[derive(Default)]
+struct ByteCounter(u64);
+
+impl Write for ByteCounter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Writes the gzipped artifact next to the artifact, e.g. `contract.wasm.gz`, and returns its path.
+pub fn write_gzipped(wasm_path: &Path) -> Result<PathBuf> {
+    let wasm = fs::read(wasm_path).context(format!("Failed read from {}", wasm_path.display()))?;
+    let gzipped_path = gzipped_path(wasm_path);
+    fs::write(&gzipped_path, gzip(&wasm)?)
+        .context(format!("Failed write into {}", gzipped_path.display()))?;
+
+    Ok(gzipped_path)
+}
+
+/// Returns the path of the gzipped artifact.
+pub fn gzipped_path(wasm_path: &Path) -> PathBuf {
+    let mut gzipped_path = wasm_path.as_os_str().to_owned();
+    gzipped_path.push(".");
+    gzipped_path.push(GZ);
+    PathBuf::from(gzipped_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use flate2::read::GzDecoder;
+
+    use crate::compression::{gzip, gzipped_size};
+
+    #[test]
+    fn gzips_deterministically() {
+        let data = b"\0asm\x01\0\0\0".repeat(64);
+        let gzipped = gzip(&data).unwrap();
+        assert_eq!(gzipped, gzip(&data).unwrap());
+        assert_eq!(gzipped.len() as u64, gzipped_size(&data).unwrap());
+
+        let mut decompressed = vec![];
+        GzDecoder::new(gzipped.as_slice())
+            .read_to_end(&mut decompressed)
+            .unwrap();
+        assert_eq!(data, decompressed);
+    }
+}
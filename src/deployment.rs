@@ -0,0 +1,174 @@
+use std::{collections::BTreeMap, fs, path::Path};
+
+use anyhow::{anyhow, Context, Result};
+use cargo::core::Package;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    hashing::{ArtifactChecksums, HashAlgorithm},
+    schema::SCHEMA,
+    METADATA_KEY,
+};
+
+pub const DEPLOY_MANIFEST: &str = "deploy-manifest.json";
+const DISPLAY_NAME_KEY: &str = "display-name";
+
+/// The `deploy-manifest.json` of the optimized artifacts, for deploy tooling.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeployManifest {
+    /// Entries by contract name, i.e. the package name in snake case like in the artifact filename,
+    /// which is what cw-orchestrator looks artifacts up by.
+    pub contracts: BTreeMap<String, DeployedContract>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeployedContract {
+    pub package: String,
+    pub version: String,
+    /// Configured in `[package.metadata.cw-optimizoor] display-name`, defaults to the package name.
+    pub display_name: String,
+    /// Path of the artifact, relative to the artifacts dir.
+    pub wasm: String,
+    /// Hex encoded SHA-256 checksum of the artifact.
+    pub checksum: String,
+    pub size: u64,
+    /// Dir of the JSON schemas relative to the artifacts dir, if they were generated.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema_dir: Option<String>,
+    /// Paths of the JSON schemas relative to the artifacts dir, sorted.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub schemas: Vec<String>,
+}
+
+/// Writes the `deploy-manifest.json` of the artifacts into the output dir.
+/// If `partial` is set, then the entries of any other contracts are kept.
+pub fn write_deploy_manifest(
+    contracts: &[Package],
+    artifacts: &[ArtifactChecksums],
+    output_dir: &Path,
+    partial: bool,
+) -> Result<()> {
+    let output_file = output_dir.join(DEPLOY_MANIFEST);
+    let mut manifest = match partial {
+        true => read_deploy_manifest(&output_file).unwrap_or_default(),
+        false => DeployManifest::default(),
+    };
+
+    for artifact in artifacts {
+        let package = contracts
+            .iter()
+            .find(|p| p.name().as_str() == artifact.package);
+        let display_name = package
+            .map(display_name)
+            .transpose()?
+            .flatten()
+            .unwrap_or_else(|| artifact.package.clone());
+        let (name, contract) = deployed_contract(artifact, display_name)?;
+        manifest.contracts.insert(name, contract);
+    }
+
+    fs::write(
+        &output_file,
+        serde_json::to_string_pretty(&manifest)? + "\n",
+    )
+    .context(format!("Failed write into {}", output_file.display()))?;
+    println!("    ...📝 Saved the deploy manifest to {}", DEPLOY_MANIFEST);
+
+    Ok(())
+}
+
+/// Reads a `deploy-manifest.json`.
+pub fn read_deploy_manifest(path: &Path) -> Result<DeployManifest> {
+    let contents =
+        fs::read_to_string(path).context(format!("Failed read from {}", path.display()))?;
+
+    Ok(serde_json::from_str(&contents)?)
+}
+
+/// Returns the contract name & manifest entry of the artifact.
+pub fn deployed_contract(
+    artifact: &ArtifactChecksums,
+    display_name: String,
+) -> Result<(String, DeployedContract)> {
+    let checksum = artifact
+        .digests
+        .get(&HashAlgorithm::Sha256)
+        .ok_or_else(|| anyhow!("{} has no SHA-256 digest", artifact.name))?;
+    let schemas = artifact.schemas.keys().cloned().collect::<Vec<_>>();
+
+    Ok((
+        artifact.package.replace('-', "_"),
+        DeployedContract {
+            package: artifact.package.clone(),
+            version: artifact.version.clone(),
+            display_name,
+            wasm: artifact.name.clone(),
+            checksum: checksum.clone(),
+            size: artifact.size,
+            schema_dir: (!schemas.is_empty()).then(|| format!("{}/{}", SCHEMA, artifact.package)),
+            schemas,
+        },
+    ))
+}
+
+/// Returns the display name in the `cw-optimizoor` table of the package metadata, if it's configured.
+fn display_name(package: &Package) -> Result<Option<String>> {
+    package
+        .manifest()
+        .custom_metadata()
+        .and_then(|metadata| metadata.get(METADATA_KEY))
+        .and_then(|metadata| metadata.get(DISPLAY_NAME_KEY))
+        .map(|name| {
+            name.as_str()
+                .map(String::from)
+                .ok_or_else(|| anyhow!("Invalid {}.{}: {}", METADATA_KEY, DISPLAY_NAME_KEY, name))
+        })
+        .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use crate::{
+        deployment::deployed_contract,
+        hashing::{ArtifactChecksums, HashAlgorithm},
+    };
+
+    #[test]
+    fn keys_contracts_by_snake_case_name() {
+        let artifact = ArtifactChecksums {
+            name: "cw20_base.wasm".to_string(),
+            package: "cw20-base".to_string(),
+            version: "1.0.0".to_string(),
+            size: 100,
+            gzip_size: 50,
+            digests: BTreeMap::from([(HashAlgorithm::Sha256, "aa".to_string())]),
+            gzip: None,
+            schemas: BTreeMap::from([
+                (
+                    "schema/cw20-base/raw/query.json".to_string(),
+                    "cc".to_string(),
+                ),
+                (
+                    "schema/cw20-base/cw20-base.json".to_string(),
+                    "bb".to_string(),
+                ),
+            ]),
+        };
+
+        let (name, contract) = deployed_contract(&artifact, "CW20 Base".to_string()).unwrap();
+        assert_eq!("cw20_base", name);
+        assert_eq!("CW20 Base", contract.display_name);
+        assert_eq!("cw20_base.wasm", contract.wasm);
+        assert_eq!("aa", contract.checksum);
+        assert_eq!(Some("schema/cw20-base".to_string()), contract.schema_dir);
+        assert_eq!(
+            vec![
+                "schema/cw20-base/cw20-base.json",
+                "schema/cw20-base/raw/query.json"
+            ],
+            contract.schemas
+        );
+    }
+}
@@ -0,0 +1,196 @@
+use std::{
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Context, Result};
+use git2::{
+    BranchType, DiffOptions, ObjectType, Repository, StatusOptions, TreeWalkMode, TreeWalkResult,
+    WorktreeAddOptions, WorktreePruneOptions,
+};
+use tempfile::TempDir;
+
+const WORKTREE_PREFIX: &str = "cw-optimizoor-baseline-";
+
+/// A temporary git worktree with a ref checked out, which is removed again when dropped.
+/// It's checked out on a temporary branch of the same name, since libgit2 can't add detached worktrees.
+pub struct TempWorktree {
+    /// Root of the worktree.
+    pub path: PathBuf,
+    /// The checked out commit.
+    pub commit: String,
+    repo: Repository,
+    /// Root of the working tree of the original repository.
+    workdir: PathBuf,
+    name: String,
+    _dir: TempDir,
+}
+
+impl TempWorktree {
+    /// Checks out the git ref of the repository containing the path into a temporary worktree.
+    pub fn add<P: AsRef<Path>>(path: P, git_ref: &str) -> Result<TempWorktree> {
+        let repo = Repository::discover(path)?;
+        let workdir = repo
+            .workdir()
+            .ok_or_else(|| anyhow!("Bare git repositories aren't supported"))?
+            .canonicalize()?;
+        let dir = tempfile::tempdir()?;
+        let name = format!(
+            "{}{}",
+            WORKTREE_PREFIX,
+            dir.path()
+                .file_name()
+                .map(|name| name.to_string_lossy().trim_start_matches('.').to_string())
+                .unwrap_or_default()
+        );
+        let path = dir.path().join(&name);
+
+        let commit = {
+            let commit = repo
+                .revparse_single(git_ref)
+                .and_then(|object| object.peel_to_commit())
+                .context(format!("Couldn't resolve git ref {}", git_ref))?;
+            let mut branch = repo.branch(&name, &commit, false)?;
+            let mut opts = WorktreeAddOptions::new();
+            opts.reference(Some(branch.get()));
+            if let Err(e) = repo.worktree(&name, &path, Some(&opts)) {
+                let _ = branch.delete();
+                return Err(anyhow!(
+                    "Couldn't check out {} into {}: {}",
+                    git_ref,
+                    path.display(),
+                    e
+                ));
+            }
+            commit.id().to_string()
+        };
+
+        Ok(TempWorktree {
+            path,
+            commit,
+            repo,
+            workdir,
+            name,
+            _dir: dir,
+        })
+    }
+
+    /// Maps a path in the working tree of the original repository to the same path in the worktree.
+    pub fn relocate(&self, path: &Path) -> Result<PathBuf> {
+        let relative = path
+            .canonicalize()?
+            .strip_prefix(&self.workdir)
+            .map(Path::to_path_buf)
+            .map_err(|_| anyhow!("{} isn't in the git repository", path.display()))?;
+
+        Ok(self.path.join(relative))
+    }
+}
+
+impl Drop for TempWorktree {
+    fn drop(&mut self) {
+        // the branch can only be deleted once it's no longer checked out
+        if let Ok(worktree) = self.repo.find_worktree(&self.name) {
+            let _ = worktree.prune(Some(
+                WorktreePruneOptions::new()
+                    .valid(true)
+                    .locked(true)
+                    .working_tree(true),
+            ));
+        }
+        if let Ok(mut branch) = self.repo.find_branch(&self.name, BranchType::Local) {
+            let _ = branch.delete();
+        }
+    }
+}
+
+/// Returns the files which differ between the working tree and the git ref, including untracked files.
+pub fn changed_files<P: AsRef<Path>>(path: P, git_ref: &str) -> Result<Vec<PathBuf>> {
+    let repo = Repository::discover(path)?;
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| anyhow!("Bare git repositories aren't supported"))?
+        .to_path_buf();
+    let tree = repo
+        .revparse_single(git_ref)
+        .and_then(|object| object.peel_to_tree())
+        .context(format!("Couldn't resolve git ref {}", git_ref))?;
+
+    let mut opts = DiffOptions::new();
+    opts.include_untracked(true).recurse_untracked_dirs(true);
+    let diff = repo.diff_tree_to_workdir_with_index(Some(&tree), Some(&mut opts))?;
+
+    // renames have both an old & a new path
+    let files = diff
+        .deltas()
+        .flat_map(|delta| [delta.old_file().path(), delta.new_file().path()])
+        .flatten()
+        .map(|path| workdir.join(path))
+        .collect::<BTreeSet<_>>();
+
+    Ok(files.into_iter().collect())
+}
+
+/// Returns the files in the dir at the git ref, along with their paths relative to the dir.
+/// The dir is relative to the root, which is a dir in the working tree of the repository.
+pub fn files_at(root: &Path, git_ref: &str, dir: &Path) -> Result<Vec<(PathBuf, Vec<u8>)>> {
+    let repo = Repository::discover(root)?;
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| anyhow!("Bare git repositories aren't supported"))?
+        .canonicalize()?;
+    let tree_path = root.canonicalize()?.strip_prefix(&workdir)?.join(dir);
+    let tree = repo
+        .revparse_single(git_ref)
+        .and_then(|object| object.peel_to_tree())
+        .context(format!("Couldn't resolve git ref {}", git_ref))?;
+    let subtree = tree
+        .get_path(&tree_path)
+        .and_then(|entry| entry.to_object(&repo))
+        .and_then(|object| object.peel_to_tree())
+        .context(format!("There's no {} at {}", tree_path.display(), git_ref))?;
+
+    let mut files = vec![];
+    subtree.walk(TreeWalkMode::PreOrder, |parent, entry| {
+        if entry.kind() == Some(ObjectType::Blob) {
+            if let Ok(blob) = entry
+                .to_object(&repo)
+                .and_then(|object| object.peel_to_blob())
+            {
+                let path = Path::new(parent).join(entry.name().unwrap_or_default());
+                files.push((path, blob.content().to_vec()));
+            }
+        }
+        TreeWalkResult::Ok
+    })?;
+
+    Ok(files)
+}
+
+/// Returns the commit checked out in the repository containing the path,
+/// and whether the working tree has any changes outside of the excluded dir, including untracked files.
+pub fn head_commit<P: AsRef<Path>>(path: P, excluded_dir: &Path) -> Result<(String, bool)> {
+    let repo = Repository::discover(path)?;
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| anyhow!("Bare git repositories aren't supported"))?
+        .to_path_buf();
+    let commit = repo.head()?.peel_to_commit()?.id().to_string();
+
+    let mut opts = StatusOptions::new();
+    opts.include_untracked(true).include_ignored(false);
+    let dirty = repo
+        .statuses(Some(&mut opts))?
+        .iter()
+        .filter_map(|entry| entry.path().map(|path| workdir.join(path)))
+        .any(|path| !path.starts_with(excluded_dir));
+
+    Ok((commit, dirty))
+}
+
+/// Returns the URL of the `origin` remote of the repository containing the path, if any.
+pub fn origin_url<P: AsRef<Path>>(path: P) -> Option<String> {
+    let repo = Repository::discover(path).ok()?;
+    let remote = repo.find_remote("origin").ok()?;
+    remote.url().map(String::from)
+}
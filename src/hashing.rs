@@ -1,15 +1,125 @@
 use std::{
-    fs::File,
+    collections::BTreeMap,
+    fs::{self, File},
     io,
     io::{BufReader, BufWriter, Read, Write},
-    path::PathBuf,
+    path::{Path, PathBuf},
 };
 
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, Context, Result};
 use hex::ToHex;
-use sha2::{Digest, Sha256};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256, Sha512};
 
-use crate::ext::TakeExt;
+use crate::{
+    compression::{gzipped_path, gzipped_size, GZ},
+    ext::TakeExt,
+    schema::artifact_name,
+};
+
+/// Hash algorithms of the artifact digests in `checksums.json`.
+#[derive(
+    clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum HashAlgorithm {
+    Sha256,
+    Sha512,
+    Blake3,
+}
+
+impl HashAlgorithm {
+    /// Calculates the hex encoded digest of the data.
+    pub fn digest(&self, data: &[u8]) -> String {
+        match self {
+            HashAlgorithm::Sha256 => Sha256::digest(data).encode_hex(),
+            HashAlgorithm::Sha512 => Sha512::digest(data).encode_hex(),
+            HashAlgorithm::Blake3 => blake3::hash(data).to_hex().to_string(),
+        }
+    }
+}
+
+/// The `checksums.json` manifest of the optimized artifacts.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChecksumsManifest {
+    /// Entries sorted by artifact name.
+    pub artifacts: Vec<ArtifactChecksums>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArtifactChecksums {
+    /// Filename of the artifact.
+    pub name: String,
+    pub package: String,
+    pub version: String,
+    pub size: u64,
+    /// Size of the artifact when gzipped.
+    pub gzip_size: u64,
+    pub digests: BTreeMap<HashAlgorithm, String>,
+    /// The gzipped artifact, if it was emitted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gzip: Option<GzipChecksums>,
+    /// SHA-256 digests of the contract's schema files by artifact name, if they were generated.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub schemas: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GzipChecksums {
+    /// Filename of the gzipped artifact.
+    pub name: String,
+    pub digests: BTreeMap<HashAlgorithm, String>,
+}
+
+impl ArtifactChecksums {
+    /// Calculates the sizes & digests of the artifact. SHA-256 is always included.
+    /// If `gzipped` is set, then the digests of the emitted gzipped artifact are included as well,
+    /// otherwise only its size is calculated.
+    pub fn new(
+        wasm_path: &Path,
+        package: &str,
+        version: &str,
+        algorithms: &[HashAlgorithm],
+        gzipped: bool,
+    ) -> Result<ArtifactChecksums> {
+        let wasm =
+            fs::read(wasm_path).context(format!("Failed read from {}", wasm_path.display()))?;
+        let name = wasm_path.rtake(1).display().to_string();
+        let (gzip, gzip_size) = match gzipped {
+            true => {
+                let gzipped_path = gzipped_path(wasm_path);
+                let gzipped_wasm = fs::read(&gzipped_path)
+                    .context(format!("Failed read from {}", gzipped_path.display()))?;
+                let gzip = GzipChecksums {
+                    name: format!("{}.{}", name, GZ),
+                    digests: digests(&gzipped_wasm, algorithms),
+                };
+                (Some(gzip), gzipped_wasm.len() as u64)
+            }
+            false => (None, gzipped_size(&wasm)?),
+        };
+
+        Ok(ArtifactChecksums {
+            gzip,
+            name,
+            package: package.to_string(),
+            version: version.to_string(),
+            size: wasm.len() as u64,
+            gzip_size,
+            digests: digests(&wasm, algorithms),
+            schemas: BTreeMap::new(),
+        })
+    }
+}
+
+/// Calculates the digests of the data with SHA-256 & the algorithms.
+fn digests(data: &[u8], algorithms: &[HashAlgorithm]) -> BTreeMap<HashAlgorithm, String> {
+    [HashAlgorithm::Sha256]
+        .iter()
+        .chain(algorithms)
+        .map(|algorithm| (*algorithm, algorithm.digest(data)))
+        .collect()
+}
 
 /// Calculates the SHA-256 digest of a buffer.
 pub fn sha256_digest<R: Read>(mut reader: R) -> Result<String> {
@@ -21,6 +131,48 @@ pub fn sha256_digest<R: Read>(mut reader: R) -> Result<String> {
 
 /// Calculates the SHA-256 checksums of the provided WASM artifacts, and outputs them to a file.
 pub fn write_checksums(wasm_paths: &[PathBuf], output_file: &PathBuf) -> Result<()> {
+    write_checksums_after(wasm_paths, output_file, "")
+}
+
+/// Variant of [`write_checksums()`](fn@write_checksums) which keeps the existing checksums of any other artifacts.
+pub fn update_checksums(wasm_paths: &[PathBuf], output_file: &PathBuf) -> Result<()> {
+    let filenames = wasm_paths
+        .iter()
+        .map(|wasm_path| artifact_name(wasm_path))
+        .collect::<Vec<_>>();
+    let kept = fs::read_to_string(output_file)
+        .unwrap_or_default()
+        .lines()
+        .filter(|line| match line.split_once("  ") {
+            Some((_, filename)) => !filenames.iter().any(|f| f == filename),
+            None => false,
+        })
+        .map(|line| format!("{}\n", line))
+        .collect::<String>();
+
+    write_checksums_after(wasm_paths, output_file, &kept)
+}
+
+/// Writes the `preamble` followed by the checksums of the provided WASM artifacts to a file.
+fn write_checksums_after(
+    wasm_paths: &[PathBuf],
+    output_file: &PathBuf,
+    preamble: &str,
+) -> Result<()> {
+    let mut lines = preamble.lines().map(String::from).collect::<Vec<_>>();
+    for wasm_path in wasm_paths {
+        let checksum =
+            checksum(wasm_path).context(format!("Failed read from {}", wasm_path.display()))?;
+        print!("    ...{}", &checksum);
+
+        lines.push(checksum.trim_end().to_string());
+    }
+
+    // sorted by filename, so that the file is stable across runs
+    lines.sort_by_key(|line| {
+        line.split_once("  ")
+            .map(|(_, filename)| filename.to_string())
+    });
     let mut checksums = BufWriter::new(
         File::options()
             .create(true)
@@ -28,26 +180,142 @@ pub fn write_checksums(wasm_paths: &[PathBuf], output_file: &PathBuf) -> Result<
             .truncate(true)
             .open(output_file)?,
     );
-    let _ = wasm_paths.iter().try_for_each(|wasm_path| {
-        let checksum = checksum(wasm_path)?;
-        checksums.write_all(checksum.as_bytes())?;
-
-        print!("    ...{}", &checksum);
-        anyhow::Ok(())
-    });
+    for line in lines {
+        writeln!(checksums, "{}", line)?;
+    }
 
     checksums.flush().map_err(|e| anyhow!(e))
 }
 
+/// Writes the `checksums.json` manifest with the entries, sorted by artifact name.
+/// If `partial` is set, then the entries of any other artifacts are kept.
+pub fn write_checksums_manifest(
+    mut artifacts: Vec<ArtifactChecksums>,
+    output_file: &Path,
+    partial: bool,
+) -> Result<()> {
+    if partial {
+        if let Ok(prev) = read_checksums_manifest(output_file) {
+            let kept = prev
+                .artifacts
+                .into_iter()
+                .filter(|prev| !artifacts.iter().any(|a| a.name == prev.name))
+                .collect::<Vec<_>>();
+            artifacts.extend(kept);
+        }
+    }
+    artifacts.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let manifest = ChecksumsManifest { artifacts };
+    fs::write(output_file, serde_json::to_string_pretty(&manifest)? + "\n")
+        .context(format!("Failed write into {}", output_file.display()))
+}
+
+/// Reads a `checksums.json` manifest.
+pub fn read_checksums_manifest(path: &Path) -> Result<ChecksumsManifest> {
+    let contents =
+        fs::read_to_string(path).context(format!("Failed read from {}", path.display()))?;
+
+    Ok(serde_json::from_str(&contents)?)
+}
+
+/// Reads the checksums file, and returns its digests & filenames.
+pub fn read_checksums(checksums_file: &Path) -> Result<Vec<(String, String)>> {
+    let checksums = fs::read_to_string(checksums_file)
+        .map_err(|e| anyhow!("Failed read from {}: {}", checksums_file.display(), e))?;
+
+    Ok(checksums
+        .lines()
+        .filter_map(|line| line.split_once("  "))
+        .map(|(digest, filename)| (digest.to_string(), filename.to_string()))
+        .collect())
+}
+
+/// Calculates the SHA-256 digest of a file.
+pub fn file_sha256_digest(path: &Path) -> Result<String> {
+    let input = File::open(path)?;
+    sha256_digest(BufReader::new(input))
+}
+
 /// Calculates the checksum of a provided artifact.
-pub fn checksum(wasm_path: &PathBuf) -> Result<String> {
-    let input = File::open(wasm_path)?;
-    let reader = BufReader::new(input);
+pub fn checksum(wasm_path: &Path) -> Result<String> {
     let checksum = format!(
         "{}  {}\n",
-        sha256_digest(reader)?,
-        wasm_path.rtake(1).display(),
+        file_sha256_digest(wasm_path)?,
+        artifact_name(wasm_path),
     );
 
     Ok(checksum)
 }
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::{
+        compression::{gzip, write_gzipped},
+        hashing::{checksum, update_checksums, write_checksums, ArtifactChecksums, HashAlgorithm},
+    };
+
+    #[test]
+    fn updates_checksums_of_given_artifacts_only() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let (a, b, output_file) = (
+            dir.path().join("a.wasm"),
+            dir.path().join("b.wasm"),
+            dir.path().join("sums.txt"),
+        );
+        fs::write(&a, "a")?;
+        fs::write(&b, "b")?;
+        write_checksums(&[a.clone(), b.clone()], &output_file)?;
+
+        fs::write(&a, "changed")?;
+        update_checksums(std::slice::from_ref(&a), &output_file)?;
+
+        assert_eq!(
+            format!("{}{}", checksum(&a)?, checksum(&b)?),
+            fs::read_to_string(&output_file)?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn calculates_digests_of_given_algorithms() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let wasm_path = dir.path().join("a.wasm");
+        fs::write(&wasm_path, "abc")?;
+        write_gzipped(&wasm_path)?;
+
+        let checksums =
+            ArtifactChecksums::new(&wasm_path, "a", "0.1.0", &[HashAlgorithm::Blake3], true)?;
+        assert_eq!("a.wasm", checksums.name);
+        assert_eq!(
+            Some("a.wasm.gz"),
+            checksums.gzip.as_ref().map(|gz| gz.name.as_str())
+        );
+        assert_eq!(3, checksums.size);
+        assert_eq!(gzip(b"abc")?.len() as u64, checksums.gzip_size);
+        assert_eq!(
+            checksums.gzip_size,
+            ArtifactChecksums::new(&wasm_path, "a", "0.1.0", &[], false)?.gzip_size
+        );
+        assert_eq!(
+            vec![
+                (
+                    HashAlgorithm::Sha256,
+                    "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+                ),
+                (
+                    HashAlgorithm::Blake3,
+                    "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85"
+                ),
+            ],
+            checksums
+                .digests
+                .iter()
+                .map(|(algorithm, digest)| (*algorithm, digest.as_str()))
+                .collect::<Vec<_>>()
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,393 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use wasmparser::{ExternalKind, Parser, Payload, TypeRef, Validator, WasmFeatures};
+
+use crate::{
+    find_artifacts_dir,
+    optimization::{optimized_output_path, read_module},
+};
+
+const ENTRY_POINTS: [&str; 6] = [
+    "instantiate",
+    "execute",
+    "query",
+    "migrate",
+    "sudo",
+    "reply",
+];
+const IBC_PREFIX: &str = "ibc_";
+const CAPABILITY_PREFIX: &str = "requires_";
+const INTERFACE_VERSION_PREFIX: &str = "interface_version_";
+/// Marker export of contracts built with cosmwasm-std before 1.0.
+const LEGACY_INTERFACE_VERSION_PREFIX: &str = "cosmwasm_vm_version_";
+
+/// What reviewers look at when approving the upload of a contract.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Inspection {
+    pub size: u64,
+    /// CosmWasm interface version, e.g. `8`.
+    pub interface_version: Option<String>,
+    pub entry_points: Vec<String>,
+    /// Capabilities required from the chain, e.g. `iterator` or `stargate`.
+    pub capabilities: Vec<String>,
+    pub exports: Vec<Export>,
+    pub imports: Vec<Import>,
+    pub memories: Vec<Limits>,
+    pub tables: Vec<Limits>,
+    pub imported_functions: u32,
+    pub functions: u32,
+    pub globals: u32,
+    pub custom_sections: Vec<CustomSection>,
+    /// WASM features beyond the MVP which the module depends on, e.g. `sign_extension` or `floats`.
+    pub features: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Export {
+    pub name: String,
+    pub kind: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Import {
+    pub module: String,
+    pub name: String,
+    pub kind: String,
+}
+
+/// Limits of a memory in 64 KiB pages, or of a table in elements.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Limits {
+    pub initial: u64,
+    pub maximum: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CustomSection {
+    pub name: String,
+    pub size: u64,
+}
+
+/// Inspects an artifact, either given by its path or by its contract's package name.
+pub fn inspect<P: AsRef<Path>>(target: &str, workspace_path: P, json: bool) -> Result<()> {
+    let wasm_path = artifact_path(target, workspace_path)?;
+    // makes sure that binaryen can read the artifact, like when optimizing it
+    read_module(&wasm_path).context(format!("Failed read from {}", wasm_path.display()))?;
+    let inspection = inspect_module(&fs::read(&wasm_path)?)?;
+
+    match json {
+        true => println!("{}", serde_json::to_string_pretty(&inspection)?),
+        false => print_inspection(&wasm_path, &inspection),
+    }
+
+    Ok(())
+}
+
+/// Returns the path of the artifact: either the target itself,
+/// or the optimized artifact of the contract package with the target's name.
+fn artifact_path<P: AsRef<Path>>(target: &str, workspace_path: P) -> Result<PathBuf> {
+    let path = PathBuf::from(target);
+    if path.is_file() {
+        return Ok(path);
+    }
+
+    let artifacts_dir = find_artifacts_dir(workspace_path)?;
+    let wasm_path = PathBuf::from(format!("{}.wasm", target.replace('-', "_")));
+    let output_path = optimized_output_path(wasm_path.as_path(), artifacts_dir.as_path())?;
+    match output_path.is_file() {
+        true => Ok(output_path),
+        false => Err(anyhow!(
+            "Couldn't find the artifact {} nor {}",
+            target,
+            output_path.display()
+        )),
+    }
+}
+
+/// Inspects the exports, imports, limits, counts, custom sections & features of the WASM module.
+pub fn inspect_module(wasm: &[u8]) -> Result<Inspection> {
+    let mut inspection = Inspection {
+        size: wasm.len() as u64,
+        ..Inspection::default()
+    };
+
+    for payload in Parser::new(0).parse_all(wasm) {
+        match payload? {
+            Payload::ImportSection(imports) => {
+                for import in imports {
+                    let import = import?;
+                    match import.ty {
+                        TypeRef::Func(_) => inspection.imported_functions += 1,
+                        TypeRef::Memory(memory) => inspection.memories.push(Limits {
+                            initial: memory.initial,
+                            maximum: memory.maximum,
+                        }),
+                        TypeRef::Table(table) => inspection.tables.push(Limits {
+                            initial: table.initial as u64,
+                            maximum: table.maximum.map(u64::from),
+                        }),
+                        TypeRef::Global(_) => inspection.globals += 1,
+                        TypeRef::Tag(_) => {}
+                    }
+                    inspection.imports.push(Import {
+                        module: import.module.to_string(),
+                        name: import.name.to_string(),
+                        kind: type_ref_kind(&import.ty).to_string(),
+                    });
+                }
+            }
+            Payload::FunctionSection(functions) => inspection.functions += functions.count(),
+            Payload::GlobalSection(globals) => inspection.globals += globals.count(),
+            Payload::MemorySection(memories) => {
+                for memory in memories {
+                    let memory = memory?;
+                    inspection.memories.push(Limits {
+                        initial: memory.initial,
+                        maximum: memory.maximum,
+                    });
+                }
+            }
+            Payload::TableSection(tables) => {
+                for table in tables {
+                    let table = table?;
+                    inspection.tables.push(Limits {
+                        initial: table.ty.initial as u64,
+                        maximum: table.ty.maximum.map(u64::from),
+                    });
+                }
+            }
+            Payload::ExportSection(exports) => {
+                for export in exports {
+                    let export = export?;
+                    inspection.exports.push(Export {
+                        name: export.name.to_string(),
+                        kind: external_kind(export.kind).to_string(),
+                    });
+                }
+            }
+            Payload::CustomSection(section) => inspection.custom_sections.push(CustomSection {
+                name: section.name().to_string(),
+                size: section.data().len() as u64,
+            }),
+            _ => {}
+        }
+    }
+
+    for export in inspection.exports.iter().filter(|e| e.kind == "func") {
+        let name = export.name.as_str();
+        if ENTRY_POINTS.contains(&name) || name.starts_with(IBC_PREFIX) {
+            inspection.entry_points.push(name.to_string());
+        } else if let Some(capability) = name.strip_prefix(CAPABILITY_PREFIX) {
+            inspection.capabilities.push(capability.to_string());
+        } else if let Some(version) = name
+            .strip_prefix(INTERFACE_VERSION_PREFIX)
+            .or_else(|| name.strip_prefix(LEGACY_INTERFACE_VERSION_PREFIX))
+        {
+            inspection.interface_version = Some(version.to_string());
+        }
+    }
+    inspection.features = features(wasm)?;
+
+    Ok(inspection)
+}
+
+type DisableFeature = fn(&mut WasmFeatures);
+
+/// Returns the WASM features the module depends on, by checking whether it stops validating without them.
+fn features(wasm: &[u8]) -> Result<Vec<String>> {
+    let all = WasmFeatures {
+        relaxed_simd: true,
+        threads: true,
+        multi_memory: true,
+        exceptions: true,
+        memory64: true,
+        extended_const: true,
+        ..WasmFeatures::default()
+    };
+    Validator::new_with_features(all).validate_all(wasm)?;
+
+    let without: [(&str, DisableFeature); 15] = [
+        ("mutable_global", |f| f.mutable_global = false),
+        ("saturating_float_to_int", |f| {
+            f.saturating_float_to_int = false
+        }),
+        ("sign_extension", |f| f.sign_extension = false),
+        ("reference_types", |f| f.reference_types = false),
+        ("multi_value", |f| f.multi_value = false),
+        ("bulk_memory", |f| f.bulk_memory = false),
+        ("simd", |f| f.simd = false),
+        ("relaxed_simd", |f| f.relaxed_simd = false),
+        ("threads", |f| f.threads = false),
+        ("tail_call", |f| f.tail_call = false),
+        ("floats", |f| f.floats = false),
+        ("multi_memory", |f| f.multi_memory = false),
+        ("exceptions", |f| f.exceptions = false),
+        ("memory64", |f| f.memory64 = false),
+        ("extended_const", |f| f.extended_const = false),
+    ];
+
+    Ok(without
+        .into_iter()
+        .filter(|(_, disable)| {
+            let mut features = all;
+            disable(&mut features);
+            Validator::new_with_features(features)
+                .validate_all(wasm)
+                .is_err()
+        })
+        .map(|(name, _)| name.to_string())
+        .collect())
+}
+
+fn external_kind(kind: ExternalKind) -> &'static str {
+    match kind {
+        ExternalKind::Func => "func",
+        ExternalKind::Table => "table",
+        ExternalKind::Memory => "memory",
+        ExternalKind::Global => "global",
+        ExternalKind::Tag => "tag",
+    }
+}
+
+fn type_ref_kind(ty: &TypeRef) -> &'static str {
+    match ty {
+        TypeRef::Func(_) => "func",
+        TypeRef::Table(_) => "table",
+        TypeRef::Memory(_) => "memory",
+        TypeRef::Global(_) => "global",
+        TypeRef::Tag(_) => "tag",
+    }
+}
+
+fn print_inspection(wasm_path: &Path, inspection: &Inspection) {
+    let list = |items: &[String]| match items.is_empty() {
+        true => String::from("none"),
+        false => items.join(", "),
+    };
+    let limits = |limits: &Limits, unit: &str| match limits.maximum {
+        Some(maximum) => format!("{} - {} {}", limits.initial, maximum, unit),
+        None => format!("{} - unbounded {}", limits.initial, unit),
+    };
+
+    println!("🔎  {} ({} bytes)", wasm_path.display(), inspection.size);
+    println!(
+        "    Interface version: {}",
+        inspection.interface_version.as_deref().unwrap_or("unknown")
+    );
+    println!("    Entry points: {}", list(&inspection.entry_points));
+    println!("    Capabilities: {}", list(&inspection.capabilities));
+    println!("    Features: {}", list(&inspection.features));
+    println!(
+        "    Functions: {} defined, {} imported",
+        inspection.functions, inspection.imported_functions
+    );
+    println!("    Globals: {}", inspection.globals);
+    for memory in &inspection.memories {
+        println!("    Memory: {}", limits(memory, "pages"));
+    }
+    for table in &inspection.tables {
+        println!("    Table: {}", limits(table, "elements"));
+    }
+    println!("    Exports:");
+    for export in &inspection.exports {
+        println!("    ...{} ({})", export.name, export.kind);
+    }
+    println!("    Imports:");
+    for import in &inspection.imports {
+        println!("    ...{}.{} ({})", import.module, import.name, import.kind);
+    }
+    println!("    Custom sections:");
+    for section in &inspection.custom_sections {
+        println!("    ...{} ({} bytes)", section.name, section.size);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::inspection::{inspect_module, Limits};
+
+    fn name(name: &str) -> Vec<u8> {
+        [&[name.len() as u8], name.as_bytes()].concat()
+    }
+
+    fn section(id: u8, contents: &[u8]) -> Vec<u8> {
+        [&[id, contents.len() as u8], contents].concat()
+    }
+
+    fn export(export: &str, index: u8) -> Vec<u8> {
+        [name(export), vec![0x00, index]].concat()
+    }
+
+    #[test]
+    fn inspects_cosmwasm_modules() {
+        let wasm = [
+            vec![0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00],
+            // (type (func))
+            section(0x01, &[0x01, 0x60, 0x00, 0x00]),
+            // (import "env" "db_read" (func (type 0)))
+            section(
+                0x02,
+                &[vec![0x01], name("env"), name("db_read"), vec![0x00, 0x00]].concat(),
+            ),
+            // three functions of type 0
+            section(0x03, &[0x03, 0x00, 0x00, 0x00]),
+            // (memory 17)
+            section(0x05, &[0x01, 0x00, 0x11]),
+            section(
+                0x07,
+                &[
+                    vec![0x03],
+                    export("instantiate", 1),
+                    export("requires_iterator", 2),
+                    export("interface_version_8", 3),
+                ]
+                .concat(),
+            ),
+            // the first body uses i32.extend8_s
+            section(
+                0x0a,
+                &[
+                    0x03, 0x06, 0x00, 0x41, 0x00, 0xc0, 0x1a, 0x0b, 0x02, 0x00, 0x0b, 0x02, 0x00,
+                    0x0b,
+                ],
+            ),
+            section(0x00, &[name("producers"), vec![0x00]].concat()),
+        ]
+        .concat();
+
+        let inspection = inspect_module(&wasm).unwrap();
+        assert_eq!(Some("8"), inspection.interface_version.as_deref());
+        assert_eq!(vec!["instantiate"], inspection.entry_points);
+        assert_eq!(vec!["iterator"], inspection.capabilities);
+        assert_eq!(
+            vec!["env.db_read"],
+            inspection
+                .imports
+                .iter()
+                .map(|i| format!("{}.{}", i.module, i.name))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![Limits {
+                initial: 17,
+                maximum: None
+            }],
+            inspection.memories
+        );
+        assert_eq!(
+            (1, 3, 0),
+            (
+                inspection.imported_functions,
+                inspection.functions,
+                inspection.globals
+            )
+        );
+        assert_eq!("producers", inspection.custom_sections[0].name);
+        assert_eq!(vec!["sign_extension"], inspection.features);
+    }
+}
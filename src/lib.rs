@@ -1,73 +1,233 @@
 use std::{
+    collections::BTreeMap,
+    env,
     fs::{self, File},
     io::Read,
-    path::{Path, PathBuf},
+    path::{Component, Path, PathBuf},
 };
 
 use anyhow::{anyhow, Context, Error};
-use cargo::{core::Workspace, ops, util::interning::InternedString};
+use cargo::{
+    core::{Package, Workspace},
+    ops,
+    util::{interning::InternedString, Filesystem},
+    Config,
+};
 use path_absolutize::Absolutize;
 
-use crate::{compilation::*, ext::*, hashing::*, optimization::*};
+use crate::{
+    affected::*, cache::*, compilation::*, compression::DEFAULT_MAX_UPLOAD_SIZE, ext::*,
+    hashing::*, optimization::*,
+};
 
+pub mod address;
+pub mod affected;
+pub mod analysis;
+pub mod attestation;
+pub mod baseline;
+pub mod budget;
+pub mod build_info;
+pub mod cache;
+pub mod chain;
+pub mod codegen;
+pub mod comparison;
+pub mod compatibility;
 pub mod compilation;
+pub mod compression;
+pub mod deployment;
 pub mod ext;
+pub mod git;
 pub mod hashing;
+pub mod inspection;
+pub mod minisign;
 pub mod optimization;
+pub mod plan;
+pub mod reproducibility;
+pub mod schema;
 pub mod self_updater;
+pub mod signing;
+pub mod size_report;
+pub mod transaction;
+pub mod verification;
+pub mod wasm;
+pub mod watch;
 
 const CONTRACTS: &str = "contracts";
 const LIBRARY: &str = "library";
 const ARTIFACTS: &str = "artifacts";
+const HOME: &str = "HOME";
+/// Min number of normal components of a host path to warn about, since e.g. `/` or `/root` also match unrelated data.
+const MIN_HOST_PATH_COMPONENTS: usize = 2;
+/// Key of the `cw-optimizoor` table in the workspace & package metadata.
+pub(crate) const METADATA_KEY: &str = "cw-optimizoor";
+
+/// Options for [`run()`](fn@run).
+#[derive(clap::Args, Debug, Clone, Default)]
+pub struct Options {
+    #[clap(flatten)]
+    pub cache: RemoteCacheOptions,
+
+    /// Watch the contracts' sources, and rebuild & reoptimize the affected contracts on changes
+    #[clap(long)]
+    pub watch: bool,
+
+    /// Only compile the contracts affected by the changes since the git ref
+    #[clap(long, value_name = "GIT_REF", conflicts_with = "watch")]
+    pub changed_since: Option<String>,
+
+    /// Sign the provenance attestation with the ed25519 key, in PKCS#8 PEM format
+    #[clap(long, value_name = "KEY_PATH", env = "CW_OPTIMIZOOR_ATTESTATION_KEY")]
+    pub attestation_key: Option<PathBuf>,
+
+    /// Additional hash algorithm of the artifact digests in checksums.json, besides SHA-256
+    #[clap(long = "hash-algorithm", value_name = "ALGORITHM", value_enum)]
+    pub hash_algorithms: Vec<HashAlgorithm>,
+
+    /// Also emit a deterministically gzipped artifact next to each optimized artifact, e.g. contract.wasm.gz
+    #[clap(long)]
+    pub gzip: bool,
+
+    /// Warn when a gzipped artifact exceeds the max upload size, in bytes
+    #[clap(long, value_name = "BYTES", default_value_t = DEFAULT_MAX_UPLOAD_SIZE, requires = "gzip")]
+    pub max_upload_size: u64,
+
+    /// Also run each contract's schema generator, i.e. its `schema` bin or example, and collect the JSON schemas
+    /// into artifacts/schema/<contract>/
+    #[clap(long)]
+    pub schema: bool,
+
+    /// Also write a deploy-manifest.json mapping the contract names to their artifacts, checksums, versions & schemas,
+    /// for deploy tooling like cw-orchestrator & ts-codegen
+    #[clap(long)]
+    pub deploy_manifest: bool,
+
+    /// Also generate a Rust module exposing each contract's artifact bytes, checksum & version, for tests.
+    /// Written into artifacts/artifacts.rs unless a path is given. It includes the artifacts by their arch suffixed
+    /// filenames, so regenerate it on each machine rather than committing it
+    #[clap(long, value_name = "PATH")]
+    pub rust_module: Option<Option<PathBuf>>,
+
+    /// Validate the optimized artifacts against the chain profile, e.g. wasmvm-1.5 or one defined in the workspace metadata
+    #[clap(long, value_name = "PROFILE")]
+    pub chain: Option<String>,
+
+    /// Also build the contracts at the git ref in a temporary worktree, and report how the artifacts changed since
+    #[clap(long, value_name = "GIT_REF", conflicts_with = "watch")]
+    pub baseline: Option<String>,
+
+    /// Write the baseline report as Markdown into the file, e.g. for a PR comment
+    #[clap(long, value_name = "PATH", requires = "baseline")]
+    pub baseline_markdown: Option<PathBuf>,
+
+    /// Keep the absolute workspace, CARGO_HOME & sysroot paths in the artifacts, instead of remapping them
+    #[clap(long, global = true)]
+    pub no_remap_path_prefix: bool,
+}
 
 /// Runs cw-optimizoor against the workspace path.
 pub async fn run<P: AsRef<Path> + TakeExt<PathBuf>>(
     workspace_path: P,
+    options: &Options,
 ) -> anyhow::Result<(), Error> {
     let manifest_path = find_manifest(&workspace_path)?;
-    let cfg = config()?;
+    let cfg = config(&manifest_path, !options.no_remap_path_prefix)?;
+    if options.watch {
+        return watch::watch(&cfg, &manifest_path, options);
+    }
+
     let ws = Workspace::new(manifest_path.as_path(), &cfg).expect("couldn't create workspace");
     let output_dir = create_artifacts_dir(&ws)?;
 
-    // all ws members that are contracts
-    let all_contracts = ws
-        .members()
-        .filter(|&p| p.manifest_path().starts_with(ws.root().join(CONTRACTS)))
-        .collect::<Vec<_>>();
-
+    let all_contracts = contracts(&ws);
     if all_contracts.is_empty() {
         return Err(anyhow!("No CW contracts found. Exiting."));
     }
 
-    // collect ws members with deps with feature = library to be compiled individually
-    let individual_contracts = all_contracts
-        .iter()
-        .filter(|p| {
-            p.dependencies()
-                .iter()
-                .any(|d| d.features().contains(&InternedString::from(LIBRARY)))
-        })
-        .map(|&p| p.clone())
-        .collect::<Vec<_>>();
+    let contracts = match &options.changed_since {
+        Some(git_ref) => {
+            let changed_files = git::changed_files(ws.root(), git_ref)?;
+            let affected = affected_contracts(&ws, &changed_files);
+            println!(
+                "🔎  Contracts affected since {}: {}",
+                git_ref,
+                affected
+                    .iter()
+                    .map(|p| p.name().to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+            affected
+        }
+        None => all_contracts,
+    };
+    if contracts.is_empty() {
+        println!("🫡  Done. No contracts were affected.");
+        return Ok(());
+    }
 
-    // package names of contracts to be compiled individually
-    let individual_names = individual_contracts
+    println!("🧐️  Compiling .../{}", &manifest_path.rtake(2).display());
+    let optimized_wasms = build(
+        &cfg,
+        &ws,
+        &contracts,
+        &output_dir,
+        options.changed_since.is_some(),
+        options,
+    )?;
+    if let Some(git_ref) = &options.baseline {
+        baseline::compare_with_baseline(
+            &ws,
+            &contracts,
+            &optimized_wasms,
+            git_ref,
+            options.changed_since.is_some(),
+            options,
+        )?;
+    }
+
+    println!(
+        "🫡  Done. Saved optimized artifacts to:\n   {}",
+        ws.root().join(ARTIFACTS).display()
+    );
+
+    Ok(())
+}
+
+/// Returns all workspace members that are contracts.
+pub fn contracts(ws: &Workspace) -> Vec<Package> {
+    ws.members()
+        .filter(|&p| p.manifest_path().starts_with(ws.root().join(CONTRACTS)))
+        .cloned()
+        .collect::<Vec<_>>()
+}
+
+/// Returns the names of the contract's dependencies with the `library` feature enabled.
+/// Such contracts are compiled individually, since cargo would otherwise unify the feature into all contracts.
+pub(crate) fn library_dependencies(contract: &Package) -> Vec<String> {
+    contract
+        .dependencies()
         .iter()
-        .map(|p| p.package_id().name().to_string())
-        .collect::<Vec<_>>();
+        .filter(|d| d.features().contains(&InternedString::from(LIBRARY)))
+        .map(|d| d.package_name().to_string())
+        .collect()
+}
 
-    // package names of contracts to be compiled together
-    let common_names = all_contracts
+/// Compiles & optimizes the contracts, and saves the artifacts & their checksums into the output dir.
+/// If `partial` is set, then the checksums of any other contracts are kept in the checksum files.
+pub fn build(
+    cfg: &Config,
+    ws: &Workspace,
+    contracts: &[Package],
+    output_dir: &PathBuf,
+    partial: bool,
+    options: &Options,
+) -> anyhow::Result<Vec<OptimizedWasm>> {
+    let compiled_wasms = compile_contracts(cfg, ws, contracts, None)?;
+    let intermediate_wasm_paths = compiled_wasms
         .iter()
-        .map(|p| p.package_id().name().to_string())
-        .filter(|name| !individual_names.contains(name))
+        .map(|wasm| wasm.path.clone())
         .collect::<Vec<_>>();
 
-    println!("🧐️  Compiling .../{}", &manifest_path.rtake(2).display());
-    let mut intermediate_wasm_paths = compile(&cfg, &ws, ops::Packages::Packages(common_names))?;
-    let mut special_intermediate_wasm_paths = compile_ephemerally(&cfg, individual_contracts)?;
-    intermediate_wasm_paths.append(&mut special_intermediate_wasm_paths);
-
     println!("🤓  Intermediate checksums:");
     let mut prev_intermediate_checksums = String::new();
 
@@ -82,33 +242,240 @@ pub async fn run<P: AsRef<Path> + TakeExt<PathBuf>>(
             "Failed read from {path}",
             path = checksums_intermediate_path.display()
         ))?;
-    write_checksums(&intermediate_wasm_paths, &checksums_intermediate_path).context(format!(
+    save_checksums(
+        &intermediate_wasm_paths,
+        &checksums_intermediate_path,
+        partial,
+    )
+    .context(format!(
         "Failed write into {path}",
         path = checksums_intermediate_path.display()
     ))?;
 
+    let previous_sizes = size_report::previous_sizes(output_dir, &intermediate_wasm_paths);
     println!("🥸  Ahh I'm optimiziing");
-    let final_wasm_paths = incremental_optimizations(
-        &output_dir,
+    let optimized_wasms = incremental_optimizations(
+        output_dir,
         intermediate_wasm_paths,
         prev_intermediate_checksums,
+        RemoteCache::new(&options.cache).as_ref(),
     )?;
 
+    let final_wasm_paths = optimized_wasms
+        .iter()
+        .map(|wasm| wasm.path.clone())
+        .collect::<Vec<_>>();
+    let gzipped_wasm_paths = match options.gzip {
+        true => gzip_artifacts(&final_wasm_paths, options.max_upload_size)?,
+        false => vec![],
+    };
+
+    let schemas = match options.schema {
+        true => schema::generate_schemas(cfg, contracts, output_dir)?,
+        false => BTreeMap::new(),
+    };
+    let schema_paths = schemas.values().flatten().cloned().collect::<Vec<_>>();
+
     println!("🤓  Final checksums:");
     let checksums_path = output_dir.join("checksums.txt");
-    write_checksums(&final_wasm_paths, &checksums_path).context(format!(
+    let checksummed_paths = [
+        final_wasm_paths.as_slice(),
+        &gzipped_wasm_paths,
+        &schema_paths,
+    ]
+    .concat();
+    save_checksums(&checksummed_paths, &checksums_path, partial).context(format!(
         "Failed write into {path}",
         path = checksums_path.display()
     ))?;
 
-    println!(
-        "🫡  Done. Saved optimized artifacts to:\n   {}",
-        ws.root().join(ARTIFACTS).display()
+    size_report::report_sizes(
+        &compiled_wasms,
+        &optimized_wasms,
+        &previous_sizes,
+        output_dir,
+        partial,
+    )?;
+
+    let checksums_manifest_path = output_dir.join("checksums.json");
+    let artifact_checksums = optimized_wasms
+        .iter()
+        .filter_map(|optimized| {
+            compiled_wasms
+                .iter()
+                .find(|compiled| compiled.path == optimized.intermediate_path)
+                .map(|compiled| (compiled, optimized))
+        })
+        .map(|(compiled, optimized)| {
+            let mut checksums = ArtifactChecksums::new(
+                &optimized.path,
+                &compiled.package_name,
+                &compiled.package_version,
+                &options.hash_algorithms,
+                options.gzip,
+            )?;
+            if let Some(files) = schemas.get(&compiled.package_name) {
+                checksums.schemas = schema::schema_digests(files)?;
+            }
+            anyhow::Ok(checksums)
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    // checked against the checksums.json of the previous run, before it's overwritten
+    let prev_checksums = read_checksums_manifest(&checksums_manifest_path).unwrap_or_default();
+    let violations = budget::check_budgets(
+        &budget::size_budgets(ws, contracts)?,
+        &artifact_checksums,
+        &prev_checksums,
     );
+    if options.deploy_manifest {
+        deployment::write_deploy_manifest(contracts, &artifact_checksums, output_dir, partial)?;
+    }
+    write_checksums_manifest(artifact_checksums, &checksums_manifest_path, partial)?;
+    if let Some(path) = &options.rust_module {
+        let path = path
+            .clone()
+            .unwrap_or_else(|| output_dir.join(codegen::RUST_MODULE));
+        codegen::write_rust_module(output_dir, &path)?;
+    }
+
+    warn_about_host_paths(cfg, ws, &final_wasm_paths)?;
+    let build_info = build_info::write_build_info(
+        cfg,
+        ws,
+        &compiled_wasms,
+        &optimized_wasms,
+        &schemas,
+        output_dir,
+        partial,
+    )?;
+    attestation::write_attestation(
+        ws,
+        &build_info,
+        output_dir,
+        options.attestation_key.as_deref(),
+    )?;
+    if let Some(chain) = &options.chain {
+        chain::check_compatibility(ws, chain, &final_wasm_paths)?;
+    }
+    // only fails once all outputs are written, so that they're consistent with each other
+    budget::report_violations(&violations)?;
+
+    Ok(optimized_wasms)
+}
+
+/// Writes the gzipped artifacts, and warns about any exceeding the max upload size.
+fn gzip_artifacts(wasm_paths: &[PathBuf], max_upload_size: u64) -> anyhow::Result<Vec<PathBuf>> {
+    println!("🗜️  Gzipping artifacts");
+    wasm_paths
+        .iter()
+        .map(|wasm_path| {
+            let gzipped_path = compression::write_gzipped(wasm_path)?;
+            let size = fs::metadata(&gzipped_path)?.len();
+            if size > max_upload_size {
+                println!(
+                    "    ...⚠️  {} is {} bytes, exceeding the max upload size by {} bytes",
+                    gzipped_path.rtake(1).display(),
+                    size,
+                    size - max_upload_size
+                );
+            }
+
+            Ok(gzipped_path)
+        })
+        .collect()
+}
+
+/// Warns about any absolute host paths which still appear in the data sections of the artifacts.
+fn warn_about_host_paths(
+    cfg: &Config,
+    ws: &Workspace,
+    wasm_paths: &[PathBuf],
+) -> anyhow::Result<()> {
+    let mut host_paths = host_path_prefixes(cfg, ws.root_manifest())?
+        .into_iter()
+        .map(|(path, _)| path)
+        .collect::<Vec<_>>();
+    host_paths.extend(env::var_os(HOME).map(PathBuf::from));
+    host_paths.retain(|path| is_distinctive_host_path(path));
+
+    for wasm_path in wasm_paths {
+        let data = wasm::data(&fs::read(wasm_path)?)?;
+        for host_path in &host_paths {
+            if wasm::contains(&data, host_path.to_string_lossy().as_bytes()) {
+                println!(
+                    "    ...⚠️  {} contains the absolute host path {}",
+                    wasm_path.rtake(1).display(),
+                    host_path.display()
+                );
+            }
+        }
+    }
 
     Ok(())
 }
 
+/// Whether the host path is specific enough that its appearance in an artifact is most likely a leak.
+fn is_distinctive_host_path(path: &Path) -> bool {
+    path.components()
+        .filter(|component| matches!(component, Component::Normal(_)))
+        .count()
+        >= MIN_HOST_PATH_COMPONENTS
+}
+
+/// Compiles the contracts, and returns the created WASM artifacts.
+/// Contracts with dependencies with the `library` feature are compiled individually, and the rest together.
+/// The individually compiled contracts use their own target dirs, unless a target dir is passed in.
+pub fn compile_contracts(
+    cfg: &Config,
+    ws: &Workspace,
+    contracts: &[Package],
+    target_dir: Option<Filesystem>,
+) -> anyhow::Result<Vec<CompiledWasm>> {
+    // collect contracts with deps with feature = library to be compiled individually
+    let individual_contracts = contracts
+        .iter()
+        .filter(|p| !library_dependencies(p).is_empty())
+        .cloned()
+        .collect::<Vec<_>>();
+
+    // package names of contracts to be compiled individually
+    let individual_names = individual_contracts
+        .iter()
+        .map(|p| p.package_id().name().to_string())
+        .collect::<Vec<_>>();
+
+    // package names of contracts to be compiled together
+    let common_names = contracts
+        .iter()
+        .map(|p| p.package_id().name().to_string())
+        .filter(|name| !individual_names.contains(name))
+        .collect::<Vec<_>>();
+
+    let mut compiled_wasms = if common_names.is_empty() {
+        vec![]
+    } else {
+        compile(cfg, ws, ops::Packages::Packages(common_names))?
+    };
+    let mut special_compiled_wasms = compile_ephemerally(cfg, individual_contracts, target_dir)?;
+    compiled_wasms.append(&mut special_compiled_wasms);
+
+    Ok(compiled_wasms)
+}
+
+/// Writes the checksums, or only updates them if `partial` is set.
+fn save_checksums(
+    wasm_paths: &[PathBuf],
+    output_file: &PathBuf,
+    partial: bool,
+) -> anyhow::Result<()> {
+    if partial {
+        update_checksums(wasm_paths, output_file)
+    } else {
+        write_checksums(wasm_paths, output_file)
+    }
+}
+
 /// Find the Cargo.toml if a directory path is passed in
 pub fn find_manifest<P: AsRef<Path>>(workspace_path: P) -> anyhow::Result<PathBuf> {
     let manifest_path = match workspace_path.as_ref().absolutize()?.to_path_buf() {
@@ -132,10 +499,59 @@ pub fn find_manifest<P: AsRef<Path>>(workspace_path: P) -> anyhow::Result<PathBu
     Ok(manifest_path)
 }
 
+/// Returns the artifacts dir of the workspace at the path.
+pub fn find_artifacts_dir<P: AsRef<Path>>(workspace_path: P) -> anyhow::Result<PathBuf> {
+    let manifest_path = find_manifest(workspace_path)?;
+    let cfg = Config::default()?;
+    let ws = Workspace::new(manifest_path.as_path(), &cfg)?;
+
+    artifacts_dir(&ws)
+}
+
+/// Returns the artifacts dir.
+pub(crate) fn artifacts_dir(ws: &Workspace) -> anyhow::Result<PathBuf> {
+    Ok(ws.root().absolutize()?.to_path_buf().join(ARTIFACTS))
+}
+
+/// Returns the optimized artifacts in the artifacts dir, sorted by path. Fails if there are none.
+pub(crate) fn optimized_artifacts(artifacts_dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let mut wasm_paths = fs::read_dir(artifacts_dir)
+        .context(format!("Failed read from {}", artifacts_dir.display()))?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<Result<Vec<PathBuf>, _>>()?;
+    wasm_paths.retain(|path| {
+        path.is_file() && path.extension().and_then(|ext| ext.to_str()) == Some("wasm")
+    });
+    wasm_paths.sort();
+    if wasm_paths.is_empty() {
+        return Err(anyhow!(
+            "There are no artifacts in {}",
+            artifacts_dir.display()
+        ));
+    }
+
+    Ok(wasm_paths)
+}
+
 /// Creates the artifacts dir if it doesn't exist.
-fn create_artifacts_dir(ws: &Workspace) -> anyhow::Result<PathBuf> {
-    let output_dir = ws.root().absolutize()?.to_path_buf().join(ARTIFACTS);
+pub(crate) fn create_artifacts_dir(ws: &Workspace) -> anyhow::Result<PathBuf> {
+    let output_dir = artifacts_dir(ws)?;
     fs::create_dir_all(&output_dir)?;
 
     Ok(output_dir)
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::is_distinctive_host_path;
+
+    #[test]
+    fn skips_short_host_paths() {
+        assert!(!is_distinctive_host_path(Path::new("/")));
+        assert!(!is_distinctive_host_path(Path::new("/root")));
+        assert!(is_distinctive_host_path(Path::new("/home/user")));
+        assert!(is_distinctive_host_path(Path::new("/root/.cargo")));
+    }
+}
@@ -1,11 +1,14 @@
-use std::env;
+use std::{env, path::PathBuf};
 
 use anyhow::Result;
 use clap::Parser;
 use futures::TryFutureExt;
 use semver::Version;
 
-use cw_optimizoor::self_updater;
+use cw_optimizoor::{
+    self_updater,
+    transaction::{generate_transactions, AccessConfig, Permission, TxOptions},
+};
 
 const PKG_NAME: &str = env!("CARGO_PKG_NAME");
 const PKG_VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -22,23 +25,363 @@ enum Cargo {
 #[derive(clap::Args, Debug)]
 #[clap(author, version, about, long_about = None)]
 struct CwOptimizoor {
+    #[clap(subcommand)]
+    command: Option<Command>,
+
     /// Path to the workspace dir or Cargo.toml
     #[clap(value_parser)]
-    workspace_path: Option<std::path::PathBuf>,
+    workspace_path: Option<PathBuf>,
+
+    #[clap(flatten)]
+    options: cw_optimizoor::Options,
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum Command {
+    /// Show how each contract would be compiled & optimized, without building anything
+    Plan {
+        /// Path to the workspace dir or Cargo.toml
+        #[clap(value_parser)]
+        workspace_path: Option<PathBuf>,
+    },
+    /// Build each contract twice in fresh target dirs, and verify that the artifacts are identical
+    VerifyReproducible {
+        /// Path to the workspace dir or Cargo.toml
+        #[clap(value_parser)]
+        workspace_path: Option<PathBuf>,
+
+        /// Also build in a copy of the workspace at a different path
+        #[clap(long)]
+        relocated: bool,
+    },
+    /// Attribute the code size of the contracts to functions & crates, measured on a name-preserving build
+    Analyze {
+        /// Path to the workspace dir or Cargo.toml
+        #[clap(value_parser)]
+        workspace_path: Option<PathBuf>,
+
+        /// Only analyze the contract package, can be repeated
+        #[clap(short, long = "package", value_name = "SPEC")]
+        packages: Vec<String>,
+
+        /// Number of the largest functions to show
+        #[clap(long, default_value_t = 20)]
+        top: usize,
+
+        /// Print the analysis as JSON
+        #[clap(long)]
+        json: bool,
+    },
+    /// Show the entry points, capabilities, imports, limits & WASM features of an artifact
+    Inspect {
+        /// Path to a WASM artifact, or the package name of a contract whose optimized artifact to inspect
+        #[clap(value_name = "WASM|CONTRACT")]
+        target: String,
+
+        /// Path to the workspace dir or Cargo.toml, used for finding the artifact of a contract
+        #[clap(long = "workspace", value_name = "WORKSPACE_PATH", value_parser)]
+        workspace_path: Option<PathBuf>,
+
+        /// Print the inspection as JSON
+        #[clap(long)]
+        json: bool,
+    },
+    /// Compare the interfaces of two artifacts, e.g. before a migration
+    Diff {
+        /// Path to the old WASM artifact
+        #[clap(value_parser)]
+        old: PathBuf,
+
+        /// Path to the new WASM artifact
+        #[clap(value_parser)]
+        new: PathBuf,
+
+        /// Also check the new artifact against the chain profile
+        #[clap(long, value_name = "PROFILE")]
+        chain: Option<String>,
+
+        /// Path to the workspace dir or Cargo.toml, used for finding the chain profiles it defines
+        #[clap(long = "workspace", value_name = "WORKSPACE_PATH", value_parser)]
+        workspace_path: Option<PathBuf>,
+
+        /// Print the diff as JSON
+        #[clap(long)]
+        json: bool,
+    },
+    /// Check the contracts' message schemas for changes breaking migrations, against a baseline
+    SchemaDiff {
+        /// Artifacts dir of the baseline, or a git ref at which the artifacts dir was committed
+        #[clap(value_name = "DIR|GIT_REF")]
+        baseline: String,
+
+        /// Path to the workspace dir or Cargo.toml
+        #[clap(long = "workspace", value_name = "WORKSPACE_PATH", value_parser)]
+        workspace_path: Option<PathBuf>,
+
+        /// Print the changes as JSON
+        #[clap(long)]
+        json: bool,
+    },
+    /// Predict the instantiate2 addresses of the artifacts
+    PredictAddress {
+        /// Bech32 address of the account instantiating the contracts
+        #[clap(long, value_name = "ADDRESS")]
+        creator: String,
+
+        /// Salt of the instantiation, as UTF-8
+        #[clap(long)]
+        salt: String,
+
+        /// Decode the salt from hex instead
+        #[clap(long)]
+        hex_salt: bool,
+
+        /// Instantiate msg, for instantiations with fix_msg set
+        #[clap(long, value_name = "JSON")]
+        msg: Option<String>,
+
+        /// Path to the workspace dir or Cargo.toml
+        #[clap(long = "workspace", value_name = "WORKSPACE_PATH", value_parser)]
+        workspace_path: Option<PathBuf>,
+
+        /// Print the addresses as JSON
+        #[clap(long)]
+        json: bool,
+    },
+    /// Generate unsigned MsgStoreCode transactions & store code proposals of the artifacts, offline
+    GenTx {
+        /// Chain ID the transactions are signed for
+        #[clap(long)]
+        chain_id: String,
+
+        /// Bech32 address of the account signing the transactions
+        #[clap(long, value_name = "ADDRESS")]
+        sender: String,
+
+        /// Who may instantiate the stored code, defaults to the chain's default permission
+        #[clap(long, value_enum)]
+        instantiate_permission: Option<Permission>,
+
+        /// Address allowed to instantiate the code, with `--instantiate-permission any-of-addresses`
+        #[clap(
+            long = "allowed-address",
+            value_name = "ADDRESS",
+            requires = "instantiate_permission"
+        )]
+        allowed_addresses: Vec<String>,
+
+        /// Deposit of the proposals, e.g. 1000000ujuno
+        #[clap(long, default_value = "")]
+        deposit: String,
+
+        /// Fees of the transactions, e.g. 5000ujuno
+        #[clap(long, default_value = "")]
+        fees: String,
+
+        /// Gas limit of the transactions
+        #[clap(long, default_value_t = 5_000_000)]
+        gas: u64,
+
+        /// Path to the workspace dir or Cargo.toml
+        #[clap(long = "workspace", value_name = "WORKSPACE_PATH", value_parser)]
+        workspace_path: Option<PathBuf>,
+    },
+    /// Verify the artifacts against a provenance attestation
+    VerifyAttestation {
+        /// Path to the attestation, either a plain in-toto statement or a signed DSSE envelope
+        #[clap(value_parser)]
+        attestation_path: PathBuf,
+
+        /// Dir of the artifacts, defaults to the attestation's dir
+        #[clap(long, value_parser)]
+        artifacts_dir: Option<PathBuf>,
+
+        /// Verify the attestation's signature with the ed25519 public key, in PEM format
+        #[clap(long, value_parser)]
+        public_key: Option<PathBuf>,
+    },
+    /// Verify the artifacts against their checksums
+    Verify {
+        /// Path to the workspace dir or Cargo.toml
+        #[clap(value_parser)]
+        workspace_path: Option<PathBuf>,
+
+        /// Checksums to verify against, in sha256sum format, as bare digests or as JSON, e.g. build-info.json.
+        /// Defaults to the artifacts' checksums.txt
+        #[clap(long, value_parser)]
+        checksums: Option<PathBuf>,
+    },
+    /// Sign the optimized artifacts with an ed25519 or minisign key, into detached .sig files
+    Sign {
+        /// Path to the workspace dir or Cargo.toml
+        #[clap(value_parser)]
+        workspace_path: Option<PathBuf>,
+
+        /// Path to an ed25519 key in PKCS#8 PEM format, or an unencrypted minisign secret key
+        #[clap(long, value_name = "KEY_PATH", value_parser)]
+        key: PathBuf,
+
+        /// Only sign checksums.txt, instead of each artifact
+        #[clap(long)]
+        checksums: bool,
+    },
+    /// Verify the detached signatures of the optimized artifacts
+    VerifySignature {
+        /// Path to the workspace dir or Cargo.toml
+        #[clap(value_parser)]
+        workspace_path: Option<PathBuf>,
+
+        /// Path to an ed25519 public key in PEM format, or a minisign public key
+        #[clap(long, value_parser)]
+        public_key: PathBuf,
+
+        /// Only verify the signature of checksums.txt, and that the artifacts match it
+        #[clap(long)]
+        checksums: bool,
+    },
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let Cargo::CwOptimizoor(args) = Cargo::parse();
 
-    let workspace_path = args
-        .workspace_path
-        .unwrap_or_else(|| env::current_dir().expect("couldn't get current directory"));
-
     let current_version = PKG_VERSION.parse::<Version>()?;
     let (latest_version, run_res) = tokio::join!(
         self_updater::fetch_latest_version(PKG_NAME).unwrap_or_else(|_| current_version.clone()),
-        cw_optimizoor::run(workspace_path)
+        async {
+            match args.command {
+                Some(Command::Plan { workspace_path }) => {
+                    cw_optimizoor::plan::plan(or_current_dir(workspace_path), &args.options)
+                }
+                Some(Command::VerifyReproducible {
+                    workspace_path,
+                    relocated,
+                }) => cw_optimizoor::reproducibility::verify_reproducible(
+                    or_current_dir(workspace_path),
+                    relocated,
+                    &args.options,
+                ),
+                Some(Command::Analyze {
+                    workspace_path,
+                    packages,
+                    top,
+                    json,
+                }) => cw_optimizoor::analysis::analyze(
+                    or_current_dir(workspace_path),
+                    &packages,
+                    top,
+                    json,
+                    &args.options,
+                ),
+                Some(Command::Inspect {
+                    target,
+                    workspace_path,
+                    json,
+                }) => cw_optimizoor::inspection::inspect(
+                    &target,
+                    or_current_dir(workspace_path),
+                    json,
+                ),
+                Some(Command::Diff {
+                    old,
+                    new,
+                    chain,
+                    workspace_path,
+                    json,
+                }) => cw_optimizoor::comparison::diff(
+                    &old,
+                    &new,
+                    or_current_dir(workspace_path),
+                    chain.as_deref(),
+                    json,
+                ),
+                Some(Command::SchemaDiff {
+                    baseline,
+                    workspace_path,
+                    json,
+                }) => cw_optimizoor::compatibility::schema_diff(
+                    or_current_dir(workspace_path),
+                    &baseline,
+                    json,
+                ),
+                Some(Command::PredictAddress {
+                    creator,
+                    salt,
+                    hex_salt,
+                    msg,
+                    workspace_path,
+                    json,
+                }) => cw_optimizoor::address::predict_addresses(
+                    or_current_dir(workspace_path),
+                    &creator,
+                    &salt,
+                    hex_salt,
+                    msg.as_deref(),
+                    json,
+                ),
+                Some(Command::GenTx {
+                    chain_id,
+                    sender,
+                    instantiate_permission,
+                    allowed_addresses,
+                    deposit,
+                    fees,
+                    gas,
+                    workspace_path,
+                }) => instantiate_permission
+                    .map(|permission| AccessConfig::new(permission, &allowed_addresses))
+                    .transpose()
+                    .and_then(|instantiate_permission| {
+                        let options = TxOptions {
+                            chain_id,
+                            sender,
+                            instantiate_permission,
+                            deposit,
+                            fees,
+                            gas_limit: gas,
+                        };
+                        generate_transactions(or_current_dir(workspace_path), &options)
+                    })
+                    .map(|_| ()),
+                Some(Command::VerifyAttestation {
+                    attestation_path,
+                    artifacts_dir,
+                    public_key,
+                }) => cw_optimizoor::attestation::verify_attestation(
+                    &attestation_path,
+                    artifacts_dir.as_deref(),
+                    public_key.as_deref(),
+                ),
+                Some(Command::Verify {
+                    workspace_path,
+                    checksums,
+                }) => cw_optimizoor::verification::verify_checksums(
+                    or_current_dir(workspace_path),
+                    checksums.as_deref(),
+                ),
+                Some(Command::Sign {
+                    workspace_path,
+                    key,
+                    checksums,
+                }) => cw_optimizoor::signing::sign_artifacts(
+                    or_current_dir(workspace_path),
+                    &key,
+                    checksums,
+                ),
+                Some(Command::VerifySignature {
+                    workspace_path,
+                    public_key,
+                    checksums,
+                }) => cw_optimizoor::signing::verify_signatures(
+                    or_current_dir(workspace_path),
+                    &public_key,
+                    checksums,
+                ),
+                None => {
+                    cw_optimizoor::run(or_current_dir(args.workspace_path), &args.options).await
+                }
+            }
+        }
     );
 
     run_res?;
@@ -47,3 +390,8 @@ async fn main() -> Result<()> {
 
     Ok(())
 }
+
+/// Defaults to the current dir if no workspace path is passed in.
+fn or_current_dir(workspace_path: Option<PathBuf>) -> PathBuf {
+    workspace_path.unwrap_or_else(|| env::current_dir().expect("couldn't get current directory"))
+}
@@ -0,0 +1,217 @@
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use blake2::{digest::consts::U32, Blake2b, Blake2b512, Digest};
+use ed25519_compact::{KeyPair, PublicKey, Signature};
+
+const UNTRUSTED_COMMENT: &str = "untrusted comment: ";
+const TRUSTED_COMMENT: &str = "trusted comment: ";
+/// Signature algorithm of keys & legacy signatures, over the data itself.
+const ALG_ED25519: &[u8; 2] = b"Ed";
+/// Signature algorithm of signatures over the BLAKE2b-512 hash of the data.
+const ALG_HASHED_ED25519: &[u8; 2] = b"ED";
+const KDF_NONE: &[u8; 2] = &[0, 0];
+const CHECKSUM_BLAKE2B: &[u8; 2] = b"B2";
+const KEY_ID_LEN: usize = 8;
+
+/// A [minisign](https://jedisct1.github.io/minisign/) secret key.
+pub struct SecretKey {
+    pub key_id: [u8; KEY_ID_LEN],
+    pub key_pair: KeyPair,
+}
+
+/// A [minisign](https://jedisct1.github.io/minisign/) public key.
+pub struct PublicKeyBox {
+    pub key_id: [u8; KEY_ID_LEN],
+    pub public_key: PublicKey,
+}
+
+/// Returns whether the contents look like a minisign key or signature file.
+pub fn is_minisign(contents: &str) -> bool {
+    contents.starts_with(UNTRUSTED_COMMENT)
+}
+
+/// Parses an unencrypted minisign secret key file, e.g. created by `minisign -G -W`.
+pub fn parse_secret_key(contents: &str) -> Result<SecretKey> {
+    let bytes = STANDARD.decode(payload_line(contents, 1)?)?;
+    if bytes.len() != 158 || &bytes[..2] != ALG_ED25519 || &bytes[4..6] != CHECKSUM_BLAKE2B {
+        return Err(anyhow!("Unsupported minisign secret key"));
+    }
+    if &bytes[2..4] != KDF_NONE {
+        return Err(anyhow!(
+            "Encrypted minisign secret keys aren't supported. Create one without a password with `minisign -G -W`"
+        ));
+    }
+
+    let key_id = &bytes[54..62];
+    let secret_key = &bytes[62..126];
+    let checksum = Blake2b::<U32>::new()
+        .chain_update(ALG_ED25519)
+        .chain_update(key_id)
+        .chain_update(secret_key)
+        .finalize();
+    if checksum.as_slice() != &bytes[126..158] {
+        return Err(anyhow!("Invalid minisign secret key checksum"));
+    }
+
+    Ok(SecretKey {
+        key_id: key_id.try_into()?,
+        key_pair: KeyPair::from_slice(secret_key)?,
+    })
+}
+
+/// Parses a minisign public key file.
+pub fn parse_public_key(contents: &str) -> Result<PublicKeyBox> {
+    let bytes = STANDARD.decode(payload_line(contents, 1)?)?;
+    if bytes.len() != 42 || &bytes[..2] != ALG_ED25519 {
+        return Err(anyhow!("Unsupported minisign public key"));
+    }
+
+    Ok(PublicKeyBox {
+        key_id: bytes[2..10].try_into()?,
+        public_key: PublicKey::from_slice(&bytes[10..])?,
+    })
+}
+
+/// Signs the BLAKE2b-512 hash of the data, and returns the contents of the minisign signature file.
+pub fn sign(secret_key: &SecretKey, data: &[u8], trusted_comment: &str) -> String {
+    let signature = secret_key.key_pair.sk.sign(Blake2b512::digest(data), None);
+    let global_signature = secret_key.key_pair.sk.sign(
+        [signature.as_ref(), trusted_comment.as_bytes()].concat(),
+        None,
+    );
+
+    format!(
+        "{}signature from cw-optimizoor secret key\n{}\n{}{}\n{}\n",
+        UNTRUSTED_COMMENT,
+        STANDARD.encode(
+            [
+                &ALG_HASHED_ED25519[..],
+                &secret_key.key_id,
+                signature.as_ref()
+            ]
+            .concat()
+        ),
+        TRUSTED_COMMENT,
+        trusted_comment,
+        STANDARD.encode(global_signature.as_ref()),
+    )
+}
+
+/// Verifies the minisign signature of the data, including its trusted comment.
+/// Both hashed & legacy signatures are supported.
+pub fn verify(public_key: &PublicKeyBox, data: &[u8], signature_file: &str) -> Result<()> {
+    let bytes = STANDARD.decode(payload_line(signature_file, 1)?)?;
+    if bytes.len() != 74 {
+        return Err(anyhow!("Invalid minisign signature"));
+    }
+    if bytes[2..10] != public_key.key_id {
+        return Err(anyhow!(
+            "Signed by a different key: {}",
+            hex::encode_upper(&bytes[2..10])
+        ));
+    }
+
+    let signature = Signature::from_slice(&bytes[10..])?;
+    match &bytes[..2] {
+        alg if alg == ALG_HASHED_ED25519 => public_key
+            .public_key
+            .verify(Blake2b512::digest(data), &signature),
+        alg if alg == ALG_ED25519 => public_key.public_key.verify(data, &signature),
+        _ => return Err(anyhow!("Unsupported minisign signature algorithm")),
+    }
+    .map_err(|_| anyhow!("Invalid signature"))?;
+
+    let trusted_comment = signature_file
+        .lines()
+        .nth(2)
+        .and_then(|line| line.strip_prefix(TRUSTED_COMMENT))
+        .ok_or_else(|| anyhow!("Missing trusted comment"))?;
+    let global_signature =
+        Signature::from_slice(&STANDARD.decode(payload_line(signature_file, 3)?)?)?;
+    public_key
+        .public_key
+        .verify(
+            [signature.as_ref(), trusted_comment.as_bytes()].concat(),
+            &global_signature,
+        )
+        .map_err(|_| anyhow!("Invalid signature of the trusted comment"))
+}
+
+/// Returns the trimmed nth line, which holds a base64 encoded payload.
+fn payload_line(contents: &str, n: usize) -> Result<&str> {
+    contents
+        .lines()
+        .nth(n)
+        .map(str::trim)
+        .ok_or_else(|| anyhow!("Invalid minisign file"))
+}
+
+#[cfg(test)]
+mod tests {
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    use blake2::{digest::consts::U32, Blake2b, Digest};
+    use ed25519_compact::{KeyPair, Seed};
+
+    use crate::minisign::{parse_public_key, parse_secret_key, sign, verify};
+
+    const KEY_ID: [u8; 8] = [1, 2, 3, 4, 5, 6, 7, 8];
+
+    fn key_files(seed: u8) -> (String, String) {
+        let key_pair = KeyPair::from_seed(Seed::new([seed; 32]));
+        let checksum = Blake2b::<U32>::new()
+            .chain_update(b"Ed")
+            .chain_update(KEY_ID)
+            .chain_update(&key_pair.sk[..])
+            .finalize();
+        let secret_key = [
+            &b"Ed"[..],
+            &[0, 0],
+            b"B2",
+            &[0; 48],
+            &KEY_ID,
+            &key_pair.sk[..],
+            &checksum,
+        ]
+        .concat();
+        let public_key = [&b"Ed"[..], &KEY_ID, &key_pair.pk[..]].concat();
+
+        (
+            format!(
+                "untrusted comment: minisign secret key\n{}\n",
+                STANDARD.encode(secret_key)
+            ),
+            format!(
+                "untrusted comment: minisign public key\n{}\n",
+                STANDARD.encode(public_key)
+            ),
+        )
+    }
+
+    #[test]
+    fn signs_and_verifies() {
+        let (secret_key, public_key) = key_files(1);
+        let (_, other_public_key) = key_files(2);
+        let secret_key = parse_secret_key(&secret_key).unwrap();
+        let public_key = parse_public_key(&public_key).unwrap();
+        let other_public_key = parse_public_key(&other_public_key).unwrap();
+
+        let signature = sign(&secret_key, b"wasm", "file:contract.wasm\thashed");
+        assert!(signature.contains("trusted comment: file:contract.wasm\thashed\n"));
+        assert!(verify(&public_key, b"wasm", &signature).is_ok());
+        assert!(verify(&public_key, b"other wasm", &signature).is_err());
+        assert!(verify(&other_public_key, b"wasm", &signature).is_err());
+
+        let tampered = signature.replace("file:contract.wasm", "file:other.wasm");
+        assert!(verify(&public_key, b"wasm", &tampered).is_err());
+    }
+
+    #[test]
+    fn rejects_encrypted_secret_keys() {
+        let (secret_key, _) = key_files(1);
+        let mut bytes = STANDARD.decode(secret_key.lines().nth(1).unwrap()).unwrap();
+        bytes[2..4].copy_from_slice(b"Sc");
+        let encrypted = format!("untrusted comment: \n{}\n", STANDARD.encode(bytes));
+
+        assert!(parse_secret_key(&encrypted).is_err());
+    }
+}
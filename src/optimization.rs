@@ -14,13 +14,37 @@ use anyhow::{anyhow, Context, Result};
 use binaryen::Module;
 use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
 
-use crate::hashing::checksum;
+use crate::{cache::RemoteCache, hashing::checksum};
+
+/// How an optimized WASM artifact was produced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    /// Optimized locally.
+    Optimized,
+    /// Unchanged since the previous run, so optimizing it was skipped.
+    Skipped,
+    /// Restored from the remote cache.
+    Restored,
+}
+
+/// An optimized WASM artifact.
+#[derive(Debug, Clone)]
+pub struct OptimizedWasm {
+    /// Path to the intermediate WASM artifact it was optimized from.
+    pub intermediate_path: PathBuf,
+    /// Path to the optimized WASM artifact.
+    pub path: PathBuf,
+    pub outcome: Outcome,
+}
 
+/// Optimizes the intermediate WASM artifacts which changed since the previous run.
+/// Changed artifacts are restored from the remote cache if possible, and optimized locally otherwise.
 pub fn incremental_optimizations(
     output_dir: &PathBuf,
     intermediate_wasm_paths: Vec<PathBuf>,
     prev_intermediate_checksums: String,
-) -> Result<Vec<PathBuf>> {
+    remote_cache: Option<&RemoteCache>,
+) -> Result<Vec<OptimizedWasm>> {
     let mut checksums = String::new();
     let checksums_path = output_dir.join("checksums.txt");
     File::options()
@@ -33,7 +57,7 @@ pub fn incremental_optimizations(
             "Failed read from {path}",
             path = checksums_path.display()
         ))?;
-    let final_wasm_paths = intermediate_wasm_paths
+    let optimized_wasms = intermediate_wasm_paths
         .par_iter()
         .map(|wasm_path| {
             let output_path = optimized_output_path(wasm_path, output_dir)?;
@@ -41,7 +65,7 @@ pub fn incremental_optimizations(
             // if optimized artifact exists,
             // and both its and prev intermediate artifact checksums match,
             // then skip optimizing it again
-            if output_path.exists()
+            let outcome = if output_path.exists()
                 && prev_intermediate_checksums
                     .contains(&checksum(wasm_path).expect("couldn't calculate checksum"))
                 && checksums.contains(&checksum(&output_path).expect("couldn't calculate checksum"))
@@ -53,6 +77,16 @@ pub fn incremental_optimizations(
                         .expect("missing file stem")
                         .to_string_lossy()
                 );
+                Outcome::Skipped
+            } else if restore_from_cache(remote_cache, wasm_path, &output_path) {
+                println!(
+                    "    ...☁️  {} was restored from the remote cache.",
+                    wasm_path
+                        .file_stem()
+                        .expect("missing file stem")
+                        .to_string_lossy()
+                );
+                Outcome::Restored
             } else {
                 optimize(wasm_path, &output_path)?;
                 println!(
@@ -62,21 +96,70 @@ pub fn incremental_optimizations(
                         .expect("missing file stem")
                         .to_string_lossy()
                 );
-            }
 
-            anyhow::Ok(output_path)
+                if let Some(Err(e)) =
+                    remote_cache.map(|cache| cache.upload(wasm_path, &output_path))
+                {
+                    println!("    ...⚠️  couldn't upload to the remote cache: {}", e);
+                }
+                Outcome::Optimized
+            };
+
+            anyhow::Ok(OptimizedWasm {
+                intermediate_path: wasm_path.clone(),
+                path: output_path,
+                outcome,
+            })
         })
-        .collect::<Result<Vec<PathBuf>>>()?;
+        .collect::<Result<Vec<OptimizedWasm>>>()?;
 
-    Ok(final_wasm_paths)
+    Ok(optimized_wasms)
 }
 
+/// Tries restoring the optimized artifact from the remote cache.
+/// Any error is reported and treated as a cache miss, so that it falls back to optimizing locally.
+fn restore_from_cache(
+    remote_cache: Option<&RemoteCache>,
+    wasm_path: &Path,
+    output_path: &Path,
+) -> bool {
+    match remote_cache.map(|cache| cache.restore(wasm_path, output_path)) {
+        Some(Ok(restored)) => restored,
+        Some(Err(e)) => {
+            println!(
+                "    ...⚠️  remote cache failed for {}: {}. Optimizing locally.",
+                wasm_path
+                    .file_stem()
+                    .expect("missing file stem")
+                    .to_string_lossy(),
+                e
+            );
+            false
+        }
+        None => false,
+    }
+}
+
+/// The binaryen/wasm-opt optimization level, equivalent to `-O2`.
+pub const OPTIMIZATION_LEVEL: u32 = 2;
+/// The binaryen/wasm-opt shrink level, equivalent to `-Oz` when combined with the optimization level.
+pub const SHRINK_LEVEL: u32 = 2;
+
 /// Optimizes the WASM artifact using binaryen/wasm-opt.
 pub fn optimize<P: AsRef<Path>>(input_path: P, output_path: P) -> Result<()> {
+    optimize_with(input_path, output_path, false)
+}
+
+/// Variant of [`optimize()`](fn@optimize) which keeps the names section.
+pub fn optimize_preserving_names<P: AsRef<Path>>(input_path: P, output_path: P) -> Result<()> {
+    optimize_with(input_path, output_path, true)
+}
+
+fn optimize_with<P: AsRef<Path>>(input_path: P, output_path: P, debug_info: bool) -> Result<()> {
     let cfg = binaryen::CodegenConfig {
-        optimization_level: 2,
-        shrink_level: 2,
-        debug_info: false,
+        optimization_level: OPTIMIZATION_LEVEL,
+        shrink_level: SHRINK_LEVEL,
+        debug_info,
     };
 
     let mut wasm = read_module(input_path.as_ref())?;
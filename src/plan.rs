@@ -0,0 +1,331 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use anyhow::{anyhow, Result};
+use cargo::core::{Package, Workspace};
+
+use crate::{
+    affected::package_roots,
+    artifacts_dir,
+    compilation::{config, intermediate_wasm_path, resolve_features},
+    contracts,
+    ext::TakeExt,
+    find_manifest,
+    hashing::checksum,
+    library_dependencies,
+    optimization::optimized_output_path,
+    Options,
+};
+
+const SRC: &str = "src";
+const CARGO_LOCK: &str = "Cargo.lock";
+
+/// How a contract would be compiled.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Group {
+    /// Compiled together with the other contracts in the workspace.
+    Common,
+    /// Compiled individually in an ephemeral workspace, because of its dependencies with the `library` feature.
+    Individual { library_deps: Vec<String> },
+}
+
+/// Whether the checksum cache would likely skip optimizing a contract.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CacheStatus {
+    LikelyHit,
+    Miss(&'static str),
+}
+
+/// The plan for building & optimizing a single contract.
+#[derive(Debug, Clone)]
+pub struct ContractPlan {
+    pub name: String,
+    pub group: Group,
+    /// Features activated for the contract itself.
+    pub features: Vec<String>,
+    /// Path to the intermediate WASM artifact.
+    pub intermediate_path: PathBuf,
+    /// Path to the optimized WASM artifact.
+    pub output_path: PathBuf,
+    pub cache: CacheStatus,
+}
+
+/// Prints how the workspace contracts would be compiled & optimized, without building anything.
+pub fn plan<P: AsRef<Path> + TakeExt<PathBuf>>(workspace_path: P, options: &Options) -> Result<()> {
+    let manifest_path = find_manifest(&workspace_path)?;
+    let cfg = config(&manifest_path, !options.no_remap_path_prefix)?;
+    let ws = Workspace::new(manifest_path.as_path(), &cfg)?;
+
+    let contracts = contracts(&ws);
+    if contracts.is_empty() {
+        return Err(anyhow!("No CW contracts found. Exiting."));
+    }
+
+    println!(
+        "📋  Build plan for .../{}",
+        &manifest_path.rtake(2).display()
+    );
+    for contract in contract_plans(&cfg, &ws, &contracts)? {
+        let group = match &contract.group {
+            Group::Common => "common".to_string(),
+            Group::Individual { library_deps } => format!(
+                "individual (depends on {} with the `library` feature)",
+                library_deps.join(", ")
+            ),
+        };
+        let features = match contract.features.is_empty() {
+            true => "none".to_string(),
+            false => contract.features.join(", "),
+        };
+        let cache = match (&contract.cache, &options.cache.url) {
+            (CacheStatus::LikelyHit, _) => "likely hit".to_string(),
+            (CacheStatus::Miss(reason), None) => format!("miss ({})", reason),
+            (CacheStatus::Miss(reason), Some(_)) => {
+                format!("miss ({}), may be restored from the remote cache", reason)
+            }
+        };
+
+        println!("    {}", contract.name);
+        println!("      group:        {}", group);
+        println!("      features:     {}", features);
+        println!(
+            "      intermediate: {}",
+            contract.intermediate_path.display()
+        );
+        println!("      output:       {}", contract.output_path.display());
+        println!("      cache:        {}", cache);
+    }
+
+    println!("🫡  Done. Nothing was built.");
+
+    Ok(())
+}
+
+/// Plans how the contracts would be compiled & optimized, grouped the same way as [`build()`](fn@crate::build).
+pub fn contract_plans(
+    cfg: &cargo::Config,
+    ws: &Workspace,
+    contracts: &[Package],
+) -> Result<Vec<ContractPlan>> {
+    let output_dir = artifacts_dir(ws)?;
+    let (individual, common): (Vec<_>, Vec<_>) = contracts
+        .iter()
+        .cloned()
+        .partition(|p| !library_dependencies(p).is_empty());
+
+    let mut plans = vec![];
+    if !common.is_empty() {
+        let features = resolve_features(cfg, ws, &common)?;
+        for contract in &common {
+            plans.push(contract_plan(
+                ws,
+                contract,
+                Group::Common,
+                &features,
+                &output_dir,
+            )?);
+        }
+    }
+    for contract in individual {
+        let ephemeral_ws = Workspace::ephemeral(contract.clone(), cfg, None, false)?;
+        let features = resolve_features(cfg, &ephemeral_ws, std::slice::from_ref(&contract))?;
+        let group = Group::Individual {
+            library_deps: library_dependencies(&contract),
+        };
+        plans.push(contract_plan(
+            &ephemeral_ws,
+            &contract,
+            group,
+            &features,
+            &output_dir,
+        )?);
+    }
+
+    Ok(plans)
+}
+
+fn contract_plan(
+    ws: &Workspace,
+    contract: &Package,
+    group: Group,
+    features: &BTreeMap<String, Vec<String>>,
+    output_dir: &PathBuf,
+) -> Result<ContractPlan> {
+    let name = contract.name().to_string();
+    let intermediate_path = intermediate_wasm_path(ws, contract)
+        .ok_or_else(|| anyhow!("Contract {} has no lib target", name))?;
+    let output_path = optimized_output_path(&intermediate_path, output_dir)?;
+    let cache = cache_status(
+        &package_roots(ws, contract),
+        &ws.root().join(CARGO_LOCK),
+        &intermediate_path,
+        &output_path,
+        output_dir,
+    );
+
+    Ok(ContractPlan {
+        features: features.get(&name).cloned().unwrap_or_default(),
+        name,
+        group,
+        intermediate_path,
+        output_path,
+        cache,
+    })
+}
+
+/// Predicts whether optimizing the contract would be skipped, based on the checksums recorded by the previous run,
+/// and whether any of its sources, i.e. the package roots & the `Cargo.lock`, were modified since its intermediate
+/// artifact was compiled.
+fn cache_status(
+    package_roots: &BTreeSet<PathBuf>,
+    cargo_lock: &Path,
+    intermediate_path: &Path,
+    output_path: &Path,
+    output_dir: &Path,
+) -> CacheStatus {
+    let recorded = |checksums_file: &str, path: &Path| match (
+        fs::read_to_string(output_dir.join(checksums_file)),
+        checksum(path),
+    ) {
+        (Ok(checksums), Ok(checksum)) => checksums.contains(&checksum),
+        _ => false,
+    };
+
+    if !output_path.exists() {
+        return CacheStatus::Miss("no optimized artifact");
+    }
+    let compiled_at = match fs::metadata(intermediate_path).and_then(|m| m.modified()) {
+        Ok(modified) => modified,
+        Err(_) => return CacheStatus::Miss("not compiled yet"),
+    };
+    if !recorded("checksums_intermediate.txt", intermediate_path) {
+        return CacheStatus::Miss("intermediate artifact changed");
+    }
+    if !recorded("checksums.txt", output_path) {
+        return CacheStatus::Miss("optimized artifact changed");
+    }
+
+    let modified = package_roots.iter().any(|root| {
+        modified_since(root, false, compiled_at)
+            || modified_since(&root.join(SRC), true, compiled_at)
+    }) || modified_since(cargo_lock, false, compiled_at);
+    if modified {
+        return CacheStatus::Miss("sources modified since the last build");
+    }
+
+    CacheStatus::LikelyHit
+}
+
+/// Returns whether the file, or any file in the dir, was modified after the given time.
+fn modified_since(path: &Path, recursive: bool, time: SystemTime) -> bool {
+    let metadata = match fs::metadata(path) {
+        Ok(metadata) => metadata,
+        Err(_) => return false,
+    };
+    if metadata.is_file() {
+        return metadata.modified().map(|m| m > time).unwrap_or(false);
+    }
+
+    fs::read_dir(path)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| recursive || path.is_file())
+        .any(|path| modified_since(&path, recursive, time))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::BTreeSet,
+        fs,
+        time::{Duration, SystemTime},
+    };
+
+    use filetime::{set_file_mtime, FileTime};
+
+    use crate::{
+        hashing::checksum,
+        plan::{cache_status, modified_since, CacheStatus},
+    };
+
+    #[test]
+    fn detects_files_modified_since() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+        fs::create_dir_all(dir.join("nested")).unwrap();
+        fs::write(dir.join("nested/lib.rs"), "").unwrap();
+
+        let before = SystemTime::now() - Duration::from_secs(60);
+        let after = SystemTime::now() + Duration::from_secs(60);
+
+        assert!(modified_since(dir, true, before));
+        assert!(!modified_since(dir, false, before));
+        assert!(!modified_since(dir, true, after));
+        assert!(!modified_since(&dir.join("missing.rs"), true, before));
+    }
+
+    #[test]
+    fn predicts_cache_hits_and_misses() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let (root, output_dir) = (dir.path().join("contract"), dir.path().join("artifacts"));
+        fs::create_dir_all(root.join("src"))?;
+        fs::create_dir_all(&output_dir)?;
+        let (cargo_lock, lib) = (dir.path().join("Cargo.lock"), root.join("src/lib.rs"));
+        let intermediate_path = dir.path().join("contract.wasm");
+        let output_path = output_dir.join("contract.wasm");
+        let status = || {
+            cache_status(
+                &BTreeSet::from([root.clone()]),
+                &cargo_lock,
+                &intermediate_path,
+                &output_path,
+                &output_dir,
+            )
+        };
+
+        assert_eq!(CacheStatus::Miss("no optimized artifact"), status());
+        fs::write(&output_path, "optimized")?;
+        assert_eq!(CacheStatus::Miss("not compiled yet"), status());
+
+        // sources older than the intermediate artifact, which is recorded along with the optimized one
+        fs::write(&cargo_lock, "")?;
+        fs::write(&lib, "")?;
+        fs::write(&intermediate_path, "intermediate")?;
+        let compiled_at = FileTime::from_system_time(SystemTime::now());
+        let earlier = FileTime::from_unix_time(compiled_at.unix_seconds() - 60, 0);
+        for path in [&cargo_lock, &lib] {
+            set_file_mtime(path, earlier)?;
+        }
+        set_file_mtime(&intermediate_path, compiled_at)?;
+        assert_eq!(CacheStatus::Miss("intermediate artifact changed"), status());
+        fs::write(
+            output_dir.join("checksums_intermediate.txt"),
+            checksum(&intermediate_path)?,
+        )?;
+        assert_eq!(CacheStatus::Miss("optimized artifact changed"), status());
+        fs::write(output_dir.join("checksums.txt"), checksum(&output_path)?)?;
+        assert_eq!(CacheStatus::LikelyHit, status());
+
+        // a source modified after the intermediate artifact was compiled
+        let later = FileTime::from_unix_time(compiled_at.unix_seconds() + 60, 0);
+        set_file_mtime(&lib, later)?;
+        assert_eq!(
+            CacheStatus::Miss("sources modified since the last build"),
+            status()
+        );
+        set_file_mtime(&lib, earlier)?;
+        set_file_mtime(&cargo_lock, later)?;
+        assert_eq!(
+            CacheStatus::Miss("sources modified since the last build"),
+            status()
+        );
+
+        Ok(())
+    }
+}
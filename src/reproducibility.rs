@@ -0,0 +1,233 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Context, Result};
+use cargo::{core::Workspace, util::Filesystem, Config};
+use tempfile::TempDir;
+
+use crate::{
+    compilation::config,
+    compile_contracts, contracts,
+    ext::TakeExt,
+    find_manifest,
+    hashing::sha256_digest,
+    optimization::{optimize, optimized_output_path},
+    wasm::{contains, diff, module_items},
+    Options, ARTIFACTS,
+};
+
+const TARGET: &str = "target";
+const GIT: &str = ".git";
+
+/// The artifacts of a single contract from one build.
+struct BuildArtifacts {
+    intermediate: Vec<u8>,
+    optimized: Vec<u8>,
+}
+
+/// The artifacts of all contracts from one build, by intermediate artifact filename.
+/// The temp dir is kept alive until the build is no longer needed.
+struct Build {
+    name: &'static str,
+    artifacts: BTreeMap<String, BuildArtifacts>,
+    _dir: TempDir,
+}
+
+/// Builds each contract twice in isolated, fresh target dirs, and optionally once more in a copy of the workspace
+/// at a different path, and verifies that all builds produce identical intermediate & optimized artifacts.
+pub fn verify_reproducible<P: AsRef<Path> + TakeExt<PathBuf>>(
+    workspace_path: P,
+    relocated: bool,
+    options: &Options,
+) -> Result<()> {
+    let manifest_path = find_manifest(&workspace_path)?;
+    let cfg = config(&manifest_path, !options.no_remap_path_prefix)?;
+    let ws_root = Workspace::new(manifest_path.as_path(), &cfg)?
+        .root()
+        .to_path_buf();
+
+    println!(
+        "🧐️  Building .../{} in fresh target dirs",
+        &manifest_path.rtake(2).display()
+    );
+    let mut builds = vec![
+        isolated_build(&cfg, &manifest_path, "first build")?,
+        isolated_build(&cfg, &manifest_path, "second build")?,
+    ];
+
+    let relocated_dir = match relocated {
+        true => Some(tempfile::tempdir()?),
+        false => None,
+    };
+    if let Some(dir) = &relocated_dir {
+        let relocated_root = dir.path().join(
+            ws_root
+                .file_name()
+                .ok_or_else(|| anyhow!("Invalid workspace path: {}", ws_root.display()))?,
+        );
+        println!("🚚  Copying the workspace to {}", relocated_root.display());
+        copy_workspace(&ws_root, &relocated_root)?;
+        let relocated_manifest = relocated_root.join(manifest_path.strip_prefix(&ws_root)?);
+        // the remapped workspace root differs for the copy
+        let relocated_cfg = config(&relocated_manifest, !options.no_remap_path_prefix)?;
+        builds.push(isolated_build(
+            &relocated_cfg,
+            &relocated_manifest,
+            "relocated build",
+        )?);
+    }
+
+    println!("🔬  Comparing builds:");
+    let (first, others) = builds.split_first().expect("missing builds");
+    let mut reproducible = true;
+    for (filename, expected) in &first.artifacts {
+        let contract = filename.trim_end_matches(".wasm");
+        let mut divergences = vec![];
+        for other in others {
+            match other.artifacts.get(filename) {
+                Some(actual) => divergences.extend(
+                    compare(expected, actual, &ws_root)?
+                        .into_iter()
+                        .map(|divergence| format!("{}: {}", other.name, divergence)),
+                ),
+                None => divergences.push(format!("{}: missing artifact", other.name)),
+            }
+        }
+
+        if divergences.is_empty() {
+            println!("    ...✅ {} is reproducible.", contract);
+        } else {
+            reproducible = false;
+            println!("    ...❌ {} differs from the {}:", contract, first.name);
+            divergences
+                .iter()
+                .for_each(|divergence| println!("         {}", divergence));
+        }
+    }
+
+    if !reproducible {
+        return Err(anyhow!("The builds aren't reproducible"));
+    }
+    println!("🫡  Done. All builds are reproducible.");
+
+    Ok(())
+}
+
+/// Compiles & optimizes all contracts of the workspace into a fresh temp dir.
+fn isolated_build(cfg: &Config, manifest_path: &Path, name: &'static str) -> Result<Build> {
+    println!("🔨  Starting the {}", name);
+    let dir = tempfile::tempdir()?;
+    let target_dir = Filesystem::new(dir.path().join(TARGET));
+    let output_dir = dir.path().join(ARTIFACTS);
+    fs::create_dir_all(&output_dir)?;
+
+    let mut ws = Workspace::new(manifest_path, cfg)?;
+    ws.set_target_dir(target_dir.clone());
+    let contracts = contracts(&ws);
+    if contracts.is_empty() {
+        return Err(anyhow!("No CW contracts found. Exiting."));
+    }
+
+    let mut artifacts = BTreeMap::new();
+    for wasm in compile_contracts(cfg, &ws, &contracts, Some(target_dir))? {
+        let wasm_path = wasm.path;
+        let output_path = optimized_output_path(&wasm_path, &output_dir)?;
+        optimize(&wasm_path, &output_path)?;
+        artifacts.insert(
+            wasm_path.rtake(1).display().to_string(),
+            BuildArtifacts {
+                intermediate: fs::read(&wasm_path)?,
+                optimized: fs::read(&output_path)?,
+            },
+        );
+    }
+
+    Ok(Build {
+        name,
+        artifacts,
+        _dir: dir,
+    })
+}
+
+/// Compares the artifacts of two builds, and describes how they diverge.
+/// The intermediate artifacts are diffed if they differ, since the optimized ones then differ as well.
+fn compare(
+    expected: &BuildArtifacts,
+    actual: &BuildArtifacts,
+    ws_root: &Path,
+) -> Result<Vec<String>> {
+    let (expected_intermediate, actual_intermediate) = (
+        sha256_digest(expected.intermediate.as_slice())?,
+        sha256_digest(actual.intermediate.as_slice())?,
+    );
+    let (expected_optimized, actual_optimized) = (
+        sha256_digest(expected.optimized.as_slice())?,
+        sha256_digest(actual.optimized.as_slice())?,
+    );
+
+    let mut divergences = vec![];
+    if expected_intermediate != actual_intermediate {
+        divergences.push(format!(
+            "intermediate checksums differ: {} vs {}",
+            expected_intermediate, actual_intermediate
+        ));
+    }
+    if expected_optimized != actual_optimized {
+        divergences.push(format!(
+            "final checksums differ: {} vs {}",
+            expected_optimized, actual_optimized
+        ));
+    }
+
+    let (expected_wasm, actual_wasm) = if expected_intermediate != actual_intermediate {
+        (&expected.intermediate, &actual.intermediate)
+    } else if expected_optimized != actual_optimized {
+        (&expected.optimized, &actual.optimized)
+    } else {
+        return Ok(divergences);
+    };
+    let (expected_items, actual_items) = (module_items(expected_wasm)?, module_items(actual_wasm)?);
+
+    let sections = diff(&expected_items.sections, &actual_items.sections);
+    if !sections.is_empty() {
+        divergences.push(format!("sections differ: {}", sections.join(", ")));
+    }
+    let functions = diff(&expected_items.functions, &actual_items.functions);
+    if !functions.is_empty() {
+        divergences.push(format!("functions differ: {}", functions.join(", ")));
+    }
+
+    // absolute paths, e.g. in panic messages, are the usual suspect
+    let root = ws_root.display().to_string();
+    if contains(expected_wasm, root.as_bytes()) {
+        divergences.push(format!(
+            "the artifact embeds the absolute workspace path {}",
+            root
+        ));
+    }
+
+    Ok(divergences)
+}
+
+/// Recursively copies the workspace, except for any build outputs & the git dir.
+fn copy_workspace(from: &Path, to: &Path) -> Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let name = entry.file_name();
+        let path = entry.path();
+        if entry.file_type()?.is_dir() {
+            if name != TARGET && name != ARTIFACTS && name != GIT {
+                copy_workspace(&path, &to.join(name))?;
+            }
+        } else {
+            fs::copy(&path, to.join(&name))
+                .context(format!("Failed to copy {}", path.display()))?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,188 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use cargo::{core::Package, Config};
+use cargo_util::ProcessBuilder;
+
+use crate::{
+    compilation::CARGO_ENCODED_RUSTFLAGS, ext::TakeExt, hashing::file_sha256_digest, ARTIFACTS,
+};
+
+/// Name of the schema generator target, and of the dir the schemas are collected into.
+pub const SCHEMA: &str = "schema";
+const JSON: &str = "json";
+
+/// Runs the schema generator of each contract, i.e. its `schema` bin or example, and collects the generated
+/// JSON files into `schema/<contract>/` in the output dir. Returns the collected files by package name.
+pub fn generate_schemas(
+    cfg: &Config,
+    contracts: &[Package],
+    output_dir: &Path,
+) -> Result<BTreeMap<String, Vec<PathBuf>>> {
+    println!("📜  Generating schemas");
+    let mut schemas = BTreeMap::new();
+    for contract in contracts {
+        let Some(target_flag) = schema_target_flag(contract) else {
+            println!("    ...⚠️  {} has no schema generator.", contract.name());
+            continue;
+        };
+
+        // generators write into ./schema, so they're run in a temp dir to keep the contract dir clean
+        let dir = tempfile::tempdir()?;
+        ProcessBuilder::new(cfg.cargo_exe()?)
+            .arg("run")
+            .arg("--quiet")
+            .arg("--manifest-path")
+            .arg(contract.manifest_path())
+            .arg(target_flag)
+            .arg(SCHEMA)
+            .cwd(dir.path())
+            // the rustflags are meant for the contracts, not for the host
+            .env_remove(CARGO_ENCODED_RUSTFLAGS)
+            .exec_with_output()
+            .context(format!(
+                "Failed to generate the schema of {}",
+                contract.name()
+            ))?;
+
+        let generated_dir = match dir.path().join(SCHEMA).is_dir() {
+            true => dir.path().join(SCHEMA),
+            false => dir.path().to_path_buf(),
+        };
+        let contract_dir = output_dir.join(SCHEMA).join(contract.name().as_str());
+        if contract_dir.exists() {
+            fs::remove_dir_all(&contract_dir)?;
+        }
+        let files = collect_json_files(&generated_dir, &contract_dir)?;
+        println!("    ...✅ {} ({} files)", contract.name(), files.len());
+        schemas.insert(contract.name().to_string(), files);
+    }
+
+    Ok(schemas)
+}
+
+/// Returns the cargo flag selecting the contract's schema generator: a `schema` bin, or else a `schema` example.
+fn schema_target_flag(contract: &Package) -> Option<&'static str> {
+    let targets = contract.targets();
+    if targets.iter().any(|t| t.is_bin() && t.name() == SCHEMA) {
+        Some("--bin")
+    } else if targets.iter().any(|t| t.is_example() && t.name() == SCHEMA) {
+        Some("--example")
+    } else {
+        None
+    }
+}
+
+/// Recursively copies the JSON files from one dir into another, and returns the copies sorted by path.
+pub fn collect_json_files(from: &Path, to: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = vec![];
+    for entry in fs::read_dir(from).context(format!("Failed read from {}", from.display()))? {
+        let path = entry?.path();
+        let target = to.join(path.rtake(1));
+        if path.is_dir() {
+            files.extend(collect_json_files(&path, &target)?);
+        } else if path.extension().and_then(|ext| ext.to_str()) == Some(JSON) {
+            fs::create_dir_all(to)?;
+            fs::copy(&path, &target).context(format!("Failed to copy {}", path.display()))?;
+            files.push(target);
+        }
+    }
+    files.sort();
+
+    Ok(files)
+}
+
+/// Returns the SHA-256 digests of the schema files, by artifact name.
+pub fn schema_digests(files: &[PathBuf]) -> Result<BTreeMap<String, String>> {
+    files
+        .iter()
+        .map(|path| Ok((artifact_name(path), file_sha256_digest(path)?)))
+        .collect()
+}
+
+/// Returns all schema files in the artifacts dir, sorted by path.
+pub fn schema_files(artifacts_dir: &Path) -> Result<Vec<PathBuf>> {
+    fn walk(dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            match path.is_dir() {
+                true => walk(&path, files)?,
+                false => files.push(path),
+            }
+        }
+        Ok(())
+    }
+
+    let mut files = vec![];
+    let schema_dir = artifacts_dir.join(SCHEMA);
+    if schema_dir.is_dir() {
+        walk(&schema_dir, &mut files)?;
+    }
+    files.sort();
+
+    Ok(files)
+}
+
+/// Returns the name of an artifact as listed in the checksums: the path relative to the artifacts dir
+/// for schema files, e.g. `schema/cw20-base/cw20-base.json`, and the filename otherwise.
+pub fn artifact_name(path: &Path) -> String {
+    let components = path
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().to_string())
+        .collect::<Vec<_>>();
+    let schema_start = components
+        .windows(2)
+        .rposition(|w| w[0] == ARTIFACTS && w[1] == SCHEMA)
+        .map(|i| i + 1)
+        .or_else(|| (components.first().map(String::as_str) == Some(SCHEMA)).then_some(0));
+
+    match schema_start {
+        Some(start) => components[start..].join("/"),
+        None => path.rtake(1).display().to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::Path};
+
+    use crate::schema::{artifact_name, collect_json_files, schema_files};
+
+    #[test]
+    fn collects_json_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let generated = dir.path().join("generated");
+        fs::create_dir_all(generated.join("raw")).unwrap();
+        fs::write(generated.join("cw20-base.json"), "{}").unwrap();
+        fs::write(generated.join("raw").join("execute.json"), "{}").unwrap();
+        fs::write(generated.join("README.md"), "").unwrap();
+
+        let artifacts_dir = dir.path().join("artifacts");
+        let files =
+            collect_json_files(&generated, &artifacts_dir.join("schema/cw20-base")).unwrap();
+        assert_eq!(files, schema_files(&artifacts_dir).unwrap());
+        assert_eq!(
+            vec![
+                "schema/cw20-base/cw20-base.json",
+                "schema/cw20-base/raw/execute.json"
+            ],
+            files.iter().map(|f| artifact_name(f)).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn names_artifacts() {
+        assert_eq!(
+            "cw20_base.wasm",
+            artifact_name(Path::new("/ws/artifacts/cw20_base.wasm"))
+        );
+        assert_eq!(
+            "schema/a/raw/query.json",
+            artifact_name(Path::new("schema/a/raw/query.json"))
+        );
+    }
+}
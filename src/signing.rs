@@ -0,0 +1,314 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use ed25519_compact::{KeyPair, PublicKey, Signature};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ext::TakeExt,
+    find_artifacts_dir,
+    hashing::{file_sha256_digest, read_checksums, sha256_digest},
+    minisign,
+};
+
+const DSSE_VERSION: &str = "DSSEv1";
+const SIG: &str = "sig";
+const CHECKSUMS: &str = "checksums.txt";
+
+/// A key for signing artifacts.
+pub enum SigningKey {
+    /// A plain ed25519 key, which creates base64 encoded signatures.
+    Ed25519(KeyPair),
+    /// A minisign key, which creates minisign signatures.
+    Minisign(minisign::SecretKey),
+}
+
+/// A key for verifying the signatures of artifacts.
+pub enum VerifyingKey {
+    Ed25519(PublicKey),
+    Minisign(minisign::PublicKeyBox),
+}
+
+impl SigningKey {
+    /// Reads either an ed25519 key in PKCS#8 PEM format, or an unencrypted minisign secret key.
+    pub fn read(path: &Path) -> Result<SigningKey> {
+        let contents =
+            fs::read_to_string(path).context(format!("Failed read from {}", path.display()))?;
+        match minisign::is_minisign(&contents) {
+            true => Ok(SigningKey::Minisign(minisign::parse_secret_key(&contents)?)),
+            false => Ok(SigningKey::Ed25519(read_key_pair(path)?)),
+        }
+    }
+
+    /// Signs the data, and returns the contents of the detached signature file.
+    pub fn sign(&self, data: &[u8], filename: &str) -> String {
+        match self {
+            SigningKey::Ed25519(key_pair) => {
+                format!(
+                    "{}\n",
+                    STANDARD.encode(key_pair.sk.sign(data, None).as_ref())
+                )
+            }
+            SigningKey::Minisign(secret_key) => {
+                minisign::sign(secret_key, data, &format!("file:{}\thashed", filename))
+            }
+        }
+    }
+}
+
+impl VerifyingKey {
+    /// Reads either an ed25519 public key in PEM format, or a minisign public key.
+    pub fn read(path: &Path) -> Result<VerifyingKey> {
+        let contents =
+            fs::read_to_string(path).context(format!("Failed read from {}", path.display()))?;
+        match minisign::is_minisign(&contents) {
+            true => Ok(VerifyingKey::Minisign(minisign::parse_public_key(
+                &contents,
+            )?)),
+            false => Ok(VerifyingKey::Ed25519(read_public_key(path)?)),
+        }
+    }
+
+    /// Verifies the data against the contents of its detached signature file.
+    pub fn verify(&self, data: &[u8], signature_file: &str) -> Result<()> {
+        match self {
+            VerifyingKey::Ed25519(public_key) => {
+                let signature = Signature::from_slice(&STANDARD.decode(signature_file.trim())?)?;
+                public_key
+                    .verify(data, &signature)
+                    .map_err(|_| anyhow!("Invalid signature"))
+            }
+            VerifyingKey::Minisign(public_key) => {
+                minisign::verify(public_key, data, signature_file)
+            }
+        }
+    }
+}
+
+/// Signs the optimized artifacts listed in `checksums.txt`, or only `checksums.txt` itself,
+/// and writes the detached `.sig` files next to them.
+pub fn sign_artifacts<P: AsRef<Path>>(
+    workspace_path: P,
+    key_path: &Path,
+    checksums_only: bool,
+) -> Result<()> {
+    let artifacts_dir = find_artifacts_dir(workspace_path)?;
+    let key = SigningKey::read(key_path)?;
+
+    println!("✍️  Signing artifacts in {}", artifacts_dir.display());
+    for path in signed_paths(&artifacts_dir, checksums_only)? {
+        let data = fs::read(&path).context(format!("Failed read from {}", path.display()))?;
+        let filename = path.rtake(1).display().to_string();
+        let signature_path = signature_path(&path);
+        fs::write(&signature_path, key.sign(&data, &filename))
+            .context(format!("Failed write into {}", signature_path.display()))?;
+        println!("    ...✅ {} was signed.", filename);
+    }
+    println!("🫡  Done.");
+
+    Ok(())
+}
+
+/// Verifies the detached signatures of the optimized artifacts listed in `checksums.txt`,
+/// or only the signature of `checksums.txt` & that the artifacts match it.
+pub fn verify_signatures<P: AsRef<Path>>(
+    workspace_path: P,
+    public_key_path: &Path,
+    checksums_only: bool,
+) -> Result<()> {
+    let artifacts_dir = find_artifacts_dir(workspace_path)?;
+    let key = VerifyingKey::read(public_key_path)?;
+
+    println!("🔬  Verifying signatures in {}", artifacts_dir.display());
+    let mut verified = true;
+    for path in signed_paths(&artifacts_dir, checksums_only)? {
+        let filename = path.rtake(1).display().to_string();
+        let signature_path = signature_path(&path);
+        let result = fs::read(&path).context("missing file").and_then(|data| {
+            let signature = fs::read_to_string(&signature_path).context("missing signature")?;
+            key.verify(&data, &signature)
+        });
+        match result {
+            Ok(()) => println!("    ...✅ {} has a valid signature.", filename),
+            Err(e) => {
+                verified = false;
+                println!("    ...❌ {}: {:#}", filename, e);
+            }
+        }
+    }
+
+    // the signed checksums vouch for the artifacts
+    if checksums_only && verified {
+        for (digest, filename) in read_checksums(&artifacts_dir.join(CHECKSUMS))? {
+            match file_sha256_digest(&artifacts_dir.join(&filename)) {
+                Ok(actual) if actual == digest => {
+                    println!("    ...✅ {} matches the signed checksum.", filename)
+                }
+                _ => {
+                    verified = false;
+                    println!("    ...❌ {} doesn't match the signed checksum.", filename);
+                }
+            }
+        }
+    }
+
+    if !verified {
+        return Err(anyhow!("Signature verification failed"));
+    }
+    println!("🫡  Done. All signatures are valid.");
+
+    Ok(())
+}
+
+/// Returns the paths of the files to sign: the artifacts listed in `checksums.txt`, or only `checksums.txt` itself.
+fn signed_paths(artifacts_dir: &Path, checksums_only: bool) -> Result<Vec<PathBuf>> {
+    let checksums_path = artifacts_dir.join(CHECKSUMS);
+    if checksums_only {
+        return Ok(vec![checksums_path]);
+    }
+
+    Ok(read_checksums(&checksums_path)?
+        .into_iter()
+        .map(|(_, filename)| artifacts_dir.join(filename))
+        .collect())
+}
+
+/// Returns the path of the detached signature file, e.g. `contract.wasm.sig`.
+fn signature_path(path: &Path) -> PathBuf {
+    let mut signature_path = path.as_os_str().to_owned();
+    signature_path.push(".");
+    signature_path.push(SIG);
+    PathBuf::from(signature_path)
+}
+
+/// A [DSSE](https://github.com/secure-systems-lab/dsse) envelope, carrying a signed payload.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Envelope {
+    /// Base64 encoded payload.
+    pub payload: String,
+    pub payload_type: String,
+    pub signatures: Vec<EnvelopeSignature>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EnvelopeSignature {
+    pub keyid: String,
+    /// Base64 encoded signature.
+    pub sig: String,
+}
+
+/// Reads an ed25519 key pair from a PKCS#8 PEM file, e.g. created by `openssl genpkey -algorithm ed25519`.
+pub fn read_key_pair(path: &Path) -> Result<KeyPair> {
+    let pem = fs::read_to_string(path).context(format!("Failed read from {}", path.display()))?;
+
+    KeyPair::from_pem(&pem).map_err(|e| anyhow!("Invalid ed25519 key {}: {}", path.display(), e))
+}
+
+/// Reads an ed25519 public key from a PEM file, e.g. created by `openssl pkey -pubout`.
+pub fn read_public_key(path: &Path) -> Result<PublicKey> {
+    let pem = fs::read_to_string(path).context(format!("Failed read from {}", path.display()))?;
+
+    PublicKey::from_pem(&pem)
+        .map_err(|e| anyhow!("Invalid ed25519 public key {}: {}", path.display(), e))
+}
+
+/// Returns the ID of the public key, i.e. the SHA-256 digest of its raw bytes.
+pub fn key_id(public_key: &PublicKey) -> Result<String> {
+    sha256_digest(&public_key[..])
+}
+
+/// Signs the payload, and wraps it in a DSSE envelope.
+pub fn sign_envelope(payload: &[u8], payload_type: &str, key_pair: &KeyPair) -> Result<Envelope> {
+    let signature = key_pair.sk.sign(pae(payload_type, payload), None);
+
+    Ok(Envelope {
+        payload: STANDARD.encode(payload),
+        payload_type: payload_type.to_string(),
+        signatures: vec![EnvelopeSignature {
+            keyid: key_id(&key_pair.pk)?,
+            sig: STANDARD.encode(signature.as_ref()),
+        }],
+    })
+}
+
+/// Verifies that the DSSE envelope was signed by the public key, and returns its payload.
+pub fn verify_envelope(envelope: &Envelope, public_key: &PublicKey) -> Result<Vec<u8>> {
+    let payload = STANDARD.decode(&envelope.payload)?;
+    let message = pae(&envelope.payload_type, &payload);
+
+    let verified = envelope.signatures.iter().any(|signature| {
+        STANDARD
+            .decode(&signature.sig)
+            .ok()
+            .and_then(|sig| Signature::from_slice(&sig).ok())
+            .map(|sig| public_key.verify(&message, &sig).is_ok())
+            .unwrap_or(false)
+    });
+    if !verified {
+        return Err(anyhow!("No valid signature by key {}", key_id(public_key)?));
+    }
+
+    Ok(payload)
+}
+
+/// Returns the DSSE pre-authentication encoding of the payload, which is what actually gets signed.
+fn pae(payload_type: &str, payload: &[u8]) -> Vec<u8> {
+    [
+        format!(
+            "{} {} {} {} ",
+            DSSE_VERSION,
+            payload_type.len(),
+            payload_type,
+            payload.len()
+        )
+        .as_bytes(),
+        payload,
+    ]
+    .concat()
+}
+
+#[cfg(test)]
+mod tests {
+    use ed25519_compact::{KeyPair, Seed};
+
+    use crate::signing::{pae, sign_envelope, verify_envelope, SigningKey, VerifyingKey};
+
+    #[test]
+    fn encodes_pae() {
+        assert_eq!(
+            b"DSSEv1 29 http://example.com/HelloWorld 11 hello world".to_vec(),
+            pae("http://example.com/HelloWorld", b"hello world")
+        );
+    }
+
+    #[test]
+    fn signs_and_verifies_envelopes() {
+        let key_pair = KeyPair::from_seed(Seed::new([1; 32]));
+        let other_key_pair = KeyPair::from_seed(Seed::new([2; 32]));
+
+        let mut envelope = sign_envelope(b"payload", "text/plain", &key_pair).unwrap();
+        assert_eq!(
+            b"payload".to_vec(),
+            verify_envelope(&envelope, &key_pair.pk).unwrap()
+        );
+        assert!(verify_envelope(&envelope, &other_key_pair.pk).is_err());
+
+        envelope.payload_type = "application/json".to_string();
+        assert!(verify_envelope(&envelope, &key_pair.pk).is_err());
+    }
+
+    #[test]
+    fn signs_and_verifies_with_ed25519_keys() {
+        let key_pair = KeyPair::from_seed(Seed::new([1; 32]));
+        let public_key = VerifyingKey::Ed25519(key_pair.pk);
+        let signature = SigningKey::Ed25519(key_pair).sign(b"wasm", "contract.wasm");
+
+        assert!(public_key.verify(b"wasm", &signature).is_ok());
+        assert!(public_key.verify(b"other wasm", &signature).is_err());
+    }
+}
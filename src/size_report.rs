@@ -0,0 +1,170 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    compilation::CompiledWasm,
+    ext::TakeExt,
+    optimization::{optimized_output_path, OptimizedWasm},
+};
+
+pub const SIZE_REPORT: &str = "size-report.json";
+
+/// Sizes of the artifacts before & after optimization, and compared to the previous run.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SizeReport {
+    /// Entries sorted by artifact name.
+    pub contracts: Vec<ContractSize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContractSize {
+    pub package: String,
+    /// Filename of the optimized artifact.
+    pub artifact: String,
+    pub intermediate_size: u64,
+    pub optimized_size: u64,
+    /// Reduction of the optimized size relative to the intermediate size, in percent.
+    pub reduction_percent: f64,
+    /// Size of the optimized artifact of the previous run, if there was one.
+    pub previous_size: Option<u64>,
+    /// Difference of the optimized size to the previous size, in bytes.
+    pub delta: Option<i64>,
+}
+
+impl ContractSize {
+    pub fn new(
+        package: &str,
+        artifact: &str,
+        intermediate_size: u64,
+        optimized_size: u64,
+        previous_size: Option<u64>,
+    ) -> ContractSize {
+        ContractSize {
+            package: package.to_string(),
+            artifact: artifact.to_string(),
+            intermediate_size,
+            optimized_size,
+            reduction_percent: match intermediate_size {
+                0 => 0.0,
+                _ => (1.0 - optimized_size as f64 / intermediate_size as f64) * 100.0,
+            },
+            previous_size,
+            delta: previous_size.map(|prev| optimized_size as i64 - prev as i64),
+        }
+    }
+}
+
+/// Returns the sizes of the optimized artifacts of the previous run, by path.
+/// Must be called before they're overwritten by the optimizations.
+pub fn previous_sizes(
+    output_dir: &Path,
+    intermediate_wasm_paths: &[PathBuf],
+) -> BTreeMap<PathBuf, u64> {
+    intermediate_wasm_paths
+        .iter()
+        .filter_map(|wasm_path| optimized_output_path(wasm_path.as_path(), output_dir).ok())
+        .filter_map(|path| fs::metadata(&path).ok().map(|m| (path, m.len())))
+        .collect()
+}
+
+/// Prints the size table, and writes it into `size-report.json` in the output dir.
+/// If `partial` is set, then the entries of any other contracts are kept.
+pub fn report_sizes(
+    compiled_wasms: &[CompiledWasm],
+    optimized_wasms: &[OptimizedWasm],
+    previous_sizes: &BTreeMap<PathBuf, u64>,
+    output_dir: &Path,
+    partial: bool,
+) -> Result<SizeReport> {
+    let mut contracts = optimized_wasms
+        .iter()
+        .filter_map(|optimized| {
+            compiled_wasms
+                .iter()
+                .find(|compiled| compiled.path == optimized.intermediate_path)
+                .map(|compiled| (compiled, optimized))
+        })
+        .map(|(compiled, optimized)| {
+            Ok(ContractSize::new(
+                &compiled.package_name,
+                &optimized.path.rtake(1).display().to_string(),
+                fs::metadata(&compiled.path)?.len(),
+                fs::metadata(&optimized.path)?.len(),
+                previous_sizes.get(&optimized.path).copied(),
+            ))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    contracts.sort_by(|a, b| a.artifact.cmp(&b.artifact));
+    print_sizes(&contracts);
+
+    let path = output_dir.join(SIZE_REPORT);
+    if partial {
+        if let Ok(prev) = read_size_report(&path) {
+            let kept = prev
+                .contracts
+                .into_iter()
+                .filter(|prev| !contracts.iter().any(|c| c.artifact == prev.artifact))
+                .collect::<Vec<_>>();
+            contracts.extend(kept);
+            contracts.sort_by(|a, b| a.artifact.cmp(&b.artifact));
+        }
+    }
+
+    let report = SizeReport { contracts };
+    fs::write(&path, serde_json::to_string_pretty(&report)? + "\n")
+        .context(format!("Failed write into {}", path.display()))?;
+
+    Ok(report)
+}
+
+/// Reads a `size-report.json`.
+pub fn read_size_report(path: &Path) -> Result<SizeReport> {
+    let contents =
+        fs::read_to_string(path).context(format!("Failed read from {}", path.display()))?;
+
+    Ok(serde_json::from_str(&contents)?)
+}
+
+fn print_sizes(contracts: &[ContractSize]) {
+    println!("📐  Sizes:");
+    println!(
+        "       {:<40} {:>12} {:>12} {:>10} {:>14}",
+        "artifact", "intermediate", "optimized", "reduction", "vs last run"
+    );
+    for contract in contracts {
+        let delta = match contract.delta {
+            Some(delta) => format!("{:+}", delta),
+            None => String::from("new"),
+        };
+        println!(
+            "    ...{:<40} {:>12} {:>12} {:>9.1}% {:>14}",
+            contract.artifact,
+            contract.intermediate_size,
+            contract.optimized_size,
+            contract.reduction_percent,
+            delta
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::size_report::ContractSize;
+
+    #[test]
+    fn calculates_reduction_and_delta() {
+        let size = ContractSize::new("a", "a.wasm", 1_000, 250, Some(300));
+        assert_eq!(75.0, size.reduction_percent);
+        assert_eq!(Some(-50), size.delta);
+
+        let size = ContractSize::new("a", "a.wasm", 0, 0, None);
+        assert_eq!(0.0, size.reduction_percent);
+        assert_eq!(None, size.delta);
+    }
+}
@@ -0,0 +1,353 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    address::{decode_address, encode_address},
+    build_info::{read_build_info, BuildInfo, BUILD_INFO},
+    compression::gzip,
+    ext::TakeExt,
+    find_artifacts_dir,
+    hashing::sha256_digest,
+    optimized_artifacts,
+};
+
+/// Dir in the artifacts dir the transactions & proposals are written into.
+pub const TX: &str = "tx";
+const MSG_STORE_CODE: &str = "/cosmwasm.wasm.v1.MsgStoreCode";
+/// Name of the governance module account, which is the sender of proposed messages.
+const GOV: &str = "gov";
+/// Length of module account addresses, in bytes.
+const MODULE_ADDRESS_LENGTH: usize = 20;
+/// Default max length of transaction memos, i.e. the Cosmos SDK's `MaxMemoCharacters`.
+const MAX_MEMO_LENGTH: usize = 256;
+const PKG_NAME: &str = env!("CARGO_PKG_NAME");
+const PKG_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Who may instantiate the stored code.
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Permission {
+    Everybody,
+    Nobody,
+    AnyOfAddresses,
+}
+
+/// The instantiate permission of a `MsgStoreCode`, as serialized by `wasmd`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccessConfig {
+    pub permission: String,
+    pub addresses: Vec<String>,
+}
+
+/// Inputs of the generated transactions & proposals.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TxOptions {
+    pub chain_id: String,
+    /// Bech32 address of the account signing the transactions.
+    pub sender: String,
+    pub instantiate_permission: Option<AccessConfig>,
+    /// Deposit of the proposals, e.g. `1000000ujuno`.
+    pub deposit: String,
+    /// Fees of the transactions, e.g. `5000ujuno`.
+    pub fees: String,
+    pub gas_limit: u64,
+}
+
+impl AccessConfig {
+    /// Addresses are only allowed, and required, for `AnyOfAddresses`, like `wasmd` validates it.
+    pub fn new(permission: Permission, addresses: &[String]) -> Result<AccessConfig> {
+        let permission = match permission {
+            Permission::AnyOfAddresses if addresses.is_empty() => {
+                return Err(anyhow!("AnyOfAddresses requires at least one address"))
+            }
+            Permission::AnyOfAddresses => "AnyOfAddresses",
+            _ if !addresses.is_empty() => {
+                return Err(anyhow!(
+                    "Allowed addresses are only supported with AnyOfAddresses"
+                ))
+            }
+            Permission::Everybody => "Everybody",
+            Permission::Nobody => "Nobody",
+        };
+        for address in addresses {
+            decode_address(address)?;
+        }
+
+        Ok(AccessConfig {
+            permission: permission.to_string(),
+            addresses: addresses.to_vec(),
+        })
+    }
+}
+
+/// Generates an unsigned `MsgStoreCode` transaction and a store code proposal for each optimized artifact
+/// in the artifacts dir, entirely offline, and writes them into `artifacts/tx/`. Returns the written paths.
+/// The transaction's memo holds the artifact's checksum & builder, and the proposal's summary the full
+/// builder metadata.
+pub fn generate_transactions<P: AsRef<Path>>(
+    workspace_path: P,
+    options: &TxOptions,
+) -> Result<Vec<PathBuf>> {
+    let artifacts_dir = find_artifacts_dir(workspace_path)?;
+    let build_info_path = artifacts_dir.join(BUILD_INFO);
+    let build_info = match build_info_path.exists() {
+        true => Some(read_build_info(&build_info_path)?),
+        false => None,
+    };
+    let (prefix, _) = decode_address(&options.sender)?;
+    let authority = module_address(&prefix, GOV)?;
+
+    let output_dir = artifacts_dir.join(TX);
+    fs::create_dir_all(&output_dir)?;
+    println!("📨  Generating transactions for {}", options.chain_id);
+    let mut paths = vec![];
+    for wasm_path in optimized_artifacts(&artifacts_dir)? {
+        let wasm =
+            fs::read(&wasm_path).context(format!("Failed read from {}", wasm_path.display()))?;
+        let artifact = wasm_path.rtake(1).display().to_string();
+        let checksum = sha256_digest(wasm.as_slice())?;
+        let wasm_byte_code = STANDARD.encode(gzip(&wasm)?);
+        let metadata = builder_metadata(&artifact, &checksum, build_info.as_ref(), options);
+
+        let stem = artifact.trim_end_matches(".wasm");
+        let tx = unsigned_tx(
+            store_code_msg(&options.sender, &wasm_byte_code, options),
+            &memo(&metadata),
+            options,
+        )?;
+        let proposal = proposal(
+            store_code_msg(&authority, &wasm_byte_code, options),
+            &artifact,
+            &metadata,
+            options,
+        )?;
+        for (kind, document) in [("store-code", tx), ("proposal", proposal)] {
+            let path = output_dir.join(format!("{}.{}.json", stem, kind));
+            fs::write(&path, serde_json::to_string_pretty(&document)? + "\n")
+                .context(format!("Failed write into {}", path.display()))?;
+            paths.push(path);
+        }
+        println!("    ...✅ {} ({})", artifact, &checksum[..12]);
+    }
+    println!(
+        "    ...📝 Saved them to {}, sign them with e.g. `<chain-binary> tx sign <file> --from {} --chain-id {}`",
+        output_dir.display(),
+        options.sender,
+        options.chain_id
+    );
+
+    Ok(paths)
+}
+
+fn store_code_msg(sender: &str, wasm_byte_code: &str, options: &TxOptions) -> Value {
+    json!({
+        "@type": MSG_STORE_CODE,
+        "sender": sender,
+        "wasm_byte_code": wasm_byte_code,
+        "instantiate_permission": options.instantiate_permission,
+    })
+}
+
+/// Wraps the message into an unsigned transaction, like generated by `tx ... --generate-only`.
+pub fn unsigned_tx(msg: Value, memo: &str, options: &TxOptions) -> Result<Value> {
+    Ok(json!({
+        "body": {
+            "messages": [msg],
+            "memo": memo,
+            "timeout_height": "0",
+            "extension_options": [],
+            "non_critical_extension_options": [],
+        },
+        "auth_info": {
+            "signer_infos": [],
+            "fee": {
+                "amount": parse_coins(&options.fees)?,
+                "gas_limit": options.gas_limit.to_string(),
+                "payer": "",
+                "granter": "",
+            },
+        },
+        "signatures": [],
+    }))
+}
+
+/// Wraps the message into a proposal, as read by `tx gov submit-proposal`. The builder metadata goes into the
+/// summary, since the proposal metadata is usually limited to 255 characters, e.g. for an IPFS link.
+fn proposal(msg: Value, artifact: &str, metadata: &Value, options: &TxOptions) -> Result<Value> {
+    parse_coins(&options.deposit)?;
+    let summary = format!(
+        "Stores {} with the SHA-256 checksum {}.\n\n{}",
+        artifact,
+        metadata["checksum"].as_str().unwrap_or_default(),
+        serde_json::to_string_pretty(metadata)?
+    );
+
+    Ok(json!({
+        "messages": [msg],
+        "metadata": "",
+        "deposit": options.deposit,
+        "title": format!("Store {}", artifact),
+        "summary": summary,
+    }))
+}
+
+/// Summarizes the builder metadata for the memo of a transaction, e.g.
+/// `cw20_base.wasm sha256:13a1…c2a5 cw-optimizoor 0.10.0`, which is limited to 256 characters by default.
+/// The artifact is left out if the memo would exceed the limit, and the rest is truncated if it still does.
+pub fn memo(metadata: &Value) -> String {
+    let field = |key: &str| metadata[key].as_str().unwrap_or_default();
+    let memo = format!(
+        "{} sha256:{} {}",
+        field("artifact"),
+        field("checksum"),
+        field("builder")
+    );
+    if memo.chars().count() <= MAX_MEMO_LENGTH {
+        return memo;
+    }
+
+    format!("sha256:{} {}", field("checksum"), field("builder"))
+        .chars()
+        .take(MAX_MEMO_LENGTH)
+        .collect()
+}
+
+/// Describes how the artifact was built, from the build info if it was generated.
+fn builder_metadata(
+    artifact: &str,
+    checksum: &str,
+    build_info: Option<&BuildInfo>,
+    options: &TxOptions,
+) -> Value {
+    let contract =
+        build_info.and_then(|info| info.contracts.iter().find(|c| c.artifact == artifact));
+    let mut metadata = json!({
+        "chain_id": options.chain_id,
+        "artifact": artifact,
+        "checksum": checksum,
+        "builder": format!("{} {}", PKG_NAME, PKG_VERSION),
+    });
+    if let Some(info) = build_info {
+        metadata["builder"] = json!(format!("{} {}", info.tool.name, info.tool.version));
+        metadata["rustc"] = json!(info.rustc);
+        if let Some(git) = &info.git {
+            metadata["commit"] = json!(git.commit);
+        }
+    }
+    if let Some(contract) = contract {
+        metadata["package"] = json!(format!("{} {}", contract.package, contract.version));
+    }
+
+    metadata
+}
+
+/// Derives the address of a module account, e.g. of the governance module.
+pub fn module_address(prefix: &str, module: &str) -> Result<String> {
+    encode_address(prefix, &Sha256::digest(module)[..MODULE_ADDRESS_LENGTH])
+}
+
+/// Parses comma separated coins, e.g. `5000ujuno,10uatom`.
+pub fn parse_coins(coins: &str) -> Result<Vec<Value>> {
+    coins
+        .split(',')
+        .map(str::trim)
+        .filter(|coin| !coin.is_empty())
+        .map(|coin| {
+            let split = coin
+                .find(|c: char| !c.is_ascii_digit())
+                .filter(|i| *i > 0)
+                .ok_or_else(|| anyhow!("Invalid coin: {}", coin))?;
+            let (amount, denom) = coin.split_at(split);
+            Ok(json!({ "denom": denom, "amount": amount }))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::{
+        address::encode_address,
+        transaction::{
+            memo, module_address, parse_coins, unsigned_tx, AccessConfig, Permission, TxOptions,
+        },
+    };
+
+    #[test]
+    fn derives_module_addresses() {
+        assert_eq!(
+            "juno10d07y265gmmuvt4z0w9aw880jnsr700jvss730",
+            module_address("juno", "gov").unwrap()
+        );
+    }
+
+    #[test]
+    fn validates_instantiate_permissions() {
+        let addresses = vec![encode_address("juno", &[0x99; 20]).unwrap()];
+
+        let config = AccessConfig::new(Permission::AnyOfAddresses, &addresses).unwrap();
+        assert_eq!("AnyOfAddresses", config.permission);
+        assert_eq!(addresses, config.addresses);
+        assert!(AccessConfig::new(Permission::AnyOfAddresses, &[]).is_err());
+        assert!(
+            AccessConfig::new(Permission::AnyOfAddresses, &["juno1invalid".to_string()]).is_err()
+        );
+
+        let config = AccessConfig::new(Permission::Everybody, &[]).unwrap();
+        assert_eq!("Everybody", config.permission);
+        assert!(config.addresses.is_empty());
+        assert!(AccessConfig::new(Permission::Everybody, &addresses).is_err());
+        assert!(AccessConfig::new(Permission::Nobody, &addresses).is_err());
+    }
+
+    #[test]
+    fn generates_unsigned_txs() {
+        let options = TxOptions {
+            fees: "5000ujuno".to_string(),
+            gas_limit: 2_000_000,
+            ..TxOptions::default()
+        };
+        let metadata = json!({
+            "artifact": "cw20_base.wasm",
+            "checksum": "aa",
+            "builder": "cw-optimizoor 0.10.0",
+        });
+        let tx = unsigned_tx(json!({ "@type": "/test" }), &memo(&metadata), &options).unwrap();
+        assert_eq!("/test", tx["body"]["messages"][0]["@type"]);
+        assert_eq!(
+            "cw20_base.wasm sha256:aa cw-optimizoor 0.10.0",
+            tx["body"]["memo"]
+        );
+        assert_eq!(
+            json!([{ "denom": "ujuno", "amount": "5000" }]),
+            tx["auth_info"]["fee"]["amount"]
+        );
+        assert_eq!("2000000", tx["auth_info"]["fee"]["gas_limit"]);
+
+        // limited in characters rather than bytes
+        let artifact = format!("{}.wasm", "ü".repeat(200));
+        let metadata =
+            json!({ "artifact": artifact, "checksum": "aa", "builder": "cw-optimizoor 0.10.0" });
+        assert_eq!(
+            artifact.clone() + " sha256:aa cw-optimizoor 0.10.0",
+            memo(&metadata)
+        );
+        let metadata = json!({ "artifact": "a".repeat(300), "checksum": "aa", "builder": "cw-optimizoor 0.10.0" });
+        assert_eq!("sha256:aa cw-optimizoor 0.10.0", memo(&metadata));
+        let metadata =
+            json!({ "artifact": "a.wasm", "checksum": "aa", "builder": "ü".repeat(300) });
+        assert_eq!(256, memo(&metadata).chars().count());
+
+        assert_eq!(2, parse_coins("1ujuno, 2uatom").unwrap().len());
+        assert!(parse_coins("").unwrap().is_empty());
+        assert!(parse_coins("ujuno").is_err());
+    }
+}
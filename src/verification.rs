@@ -0,0 +1,436 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Context, Result};
+use serde_json::Value;
+
+use crate::{
+    ext::TakeExt,
+    find_artifacts_dir,
+    hashing::file_sha256_digest,
+    schema::{artifact_name, schema_files},
+};
+
+const CHECKSUMS: &str = "checksums.txt";
+const WASM: &str = ".wasm";
+const WASM_GZ: &str = ".wasm.gz";
+const JSON: &str = ".json";
+/// Arch suffixes of the artifacts, which rust-optimizer omits on x86_64.
+const ARCH_SUFFIXES: [&str; 2] = ["-x86_64", "-aarch64"];
+
+/// Expected digests of the artifacts.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExpectedDigests {
+    /// Digests by artifact filename.
+    pub named: BTreeMap<String, String>,
+    /// Digests without a filename, e.g. on-chain code hashes.
+    pub bare: Vec<String>,
+}
+
+/// A discrepancy between the artifacts & the expected digests.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Discrepancy {
+    /// An expected artifact, or an artifact with an expected bare digest, is missing.
+    Missing(String),
+    /// An artifact isn't expected.
+    Extra(String),
+    /// An artifact doesn't match its expected digest.
+    Mismatched {
+        artifact: String,
+        expected: String,
+        actual: String,
+    },
+}
+
+/// Verifies the SHA-256 digests of the artifacts in the artifacts dir against `checksums.txt`,
+/// or against the given checksums file.
+pub fn verify_checksums<P: AsRef<Path>>(
+    workspace_path: P,
+    checksums_path: Option<&Path>,
+) -> Result<()> {
+    let artifacts_dir = find_artifacts_dir(workspace_path)?;
+    let checksums_path = checksums_path
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| artifacts_dir.join(CHECKSUMS));
+
+    println!(
+        "🔬  Verifying artifacts in {} against {}",
+        artifacts_dir.display(),
+        checksums_path.display()
+    );
+    let expected = read_expected_digests(&checksums_path)?;
+    let actual = artifact_digests(&artifacts_dir)?;
+
+    let discrepancies = discrepancies(&expected, &actual);
+    for (artifact, digest) in &actual {
+        let mismatched = discrepancies.iter().any(|d| match d {
+            Discrepancy::Extra(a) | Discrepancy::Mismatched { artifact: a, .. } => a == artifact,
+            Discrepancy::Missing(_) => false,
+        });
+        if !mismatched {
+            println!("    ...✅ {}  {}", digest, artifact);
+        }
+    }
+    for discrepancy in &discrepancies {
+        match discrepancy {
+            Discrepancy::Missing(artifact) => println!("    ...❌ {} is missing.", artifact),
+            Discrepancy::Extra(artifact) => println!("    ...❌ {} isn't expected.", artifact),
+            Discrepancy::Mismatched {
+                artifact,
+                expected,
+                actual,
+            } => println!(
+                "    ...❌ {} doesn't match: expected {}, got {}.",
+                artifact, expected, actual
+            ),
+        }
+    }
+
+    if !discrepancies.is_empty() {
+        return Err(anyhow!(
+            "{} artifact(s) don't match the checksums",
+            discrepancies.len()
+        ));
+    }
+    println!("🫡  Done. All artifacts match.");
+
+    Ok(())
+}
+
+/// Reads the expected digests from either a `sha256sum` style file, e.g. `checksums.txt` or rust-optimizer's output,
+/// a list of bare digests, e.g. on-chain code hashes, or a JSON manifest, e.g. `checksums.json` or `build-info.json`.
+pub fn read_expected_digests(path: &Path) -> Result<ExpectedDigests> {
+    let contents =
+        fs::read_to_string(path).context(format!("Failed read from {}", path.display()))?;
+
+    match contents.trim_start().starts_with(['{', '[']) {
+        true => parse_json(&serde_json::from_str(&contents)?),
+        false => parse_lines(&contents),
+    }
+}
+
+/// Parses lines of either `<digest>  <filename>`, `<digest> *<filename>` or `<digest>`.
+fn parse_lines(contents: &str) -> Result<ExpectedDigests> {
+    let mut expected = ExpectedDigests::default();
+    for line in contents.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        match line.split_once(char::is_whitespace) {
+            Some((digest, filename)) => {
+                let filename = filename.trim_start().trim_start_matches('*');
+                expected
+                    .named
+                    .insert(basename(filename), digest_of(digest)?);
+            }
+            None => expected.bare.push(digest_of(line)?),
+        }
+    }
+
+    Ok(expected)
+}
+
+/// Parses either a `build-info.json`, a `checksums.json`, a map of filenames to digests, or a list of bare digests.
+fn parse_json(value: &Value) -> Result<ExpectedDigests> {
+    let mut expected = ExpectedDigests::default();
+    match value {
+        Value::Object(map) if map.contains_key("contracts") => {
+            for contract in map["contracts"].as_array().into_iter().flatten() {
+                match (contract["artifact"].as_str(), contract["sha256"].as_str()) {
+                    (Some(artifact), Some(digest)) => {
+                        expected
+                            .named
+                            .insert(basename(artifact), digest_of(digest)?);
+                    }
+                    _ => return Err(anyhow!("Invalid contract entry: {}", contract)),
+                }
+                parse_schemas(&contract["schemas"], &mut expected)?;
+            }
+        }
+        Value::Object(map) if map.contains_key("artifacts") => {
+            let artifacts = map["artifacts"].as_array().into_iter().flatten();
+            // along with any gzipped artifacts
+            let gzipped = artifacts
+                .clone()
+                .map(|a| &a["gzip"])
+                .filter(|gz| !gz.is_null());
+            for artifact in artifacts.chain(gzipped) {
+                match (
+                    artifact["name"].as_str(),
+                    artifact["digests"]["sha256"].as_str(),
+                ) {
+                    (Some(name), Some(digest)) => {
+                        expected.named.insert(basename(name), digest_of(digest)?);
+                    }
+                    _ => return Err(anyhow!("Invalid artifact entry: {}", artifact)),
+                }
+                parse_schemas(&artifact["schemas"], &mut expected)?;
+            }
+        }
+        Value::Object(map) => {
+            for (artifact, digest) in map {
+                let digest = digest
+                    .as_str()
+                    .ok_or_else(|| anyhow!("Invalid digest of {}", artifact))?;
+                expected
+                    .named
+                    .insert(basename(artifact), digest_of(digest)?);
+            }
+        }
+        Value::Array(digests) => {
+            for digest in digests {
+                let digest = digest
+                    .as_str()
+                    .ok_or_else(|| anyhow!("Invalid digest: {}", digest))?;
+                expected.bare.push(digest_of(digest)?);
+            }
+        }
+        _ => return Err(anyhow!("Unsupported JSON checksums")),
+    }
+
+    Ok(expected)
+}
+
+/// Parses the digests of the schema files of a contract, if it lists any.
+fn parse_schemas(schemas: &Value, expected: &mut ExpectedDigests) -> Result<()> {
+    for (name, digest) in schemas.as_object().into_iter().flatten() {
+        let digest = digest
+            .as_str()
+            .ok_or_else(|| anyhow!("Invalid digest of {}", name))?;
+        expected.named.insert(basename(name), digest_of(digest)?);
+    }
+
+    Ok(())
+}
+
+/// Compares the actual digests by artifact filename to the expected digests. Artifacts are matched by their exact
+/// filename, or else by their filename without the arch suffix, e.g. `cw20_base-x86_64.wasm` matches rust-optimizer's
+/// `cw20_base.wasm`. Gzipped artifacts & schema files are only reported as extra if any of their kind are expected,
+/// since e.g. rust-optimizer's `checksums.txt` or on-chain code hashes only cover the `.wasm` artifacts.
+pub fn discrepancies(
+    expected: &ExpectedDigests,
+    actual: &BTreeMap<String, String>,
+) -> Vec<Discrepancy> {
+    let mut discrepancies = vec![];
+    let mut matched = BTreeSet::new();
+    for (artifact, expected_digest) in &expected.named {
+        let found = actual.get_key_value(artifact).or_else(|| {
+            actual.iter().find(|(a, _)| {
+                !expected.named.contains_key(*a)
+                    && !matched.contains(*a)
+                    && without_arch_suffix(a) == without_arch_suffix(artifact)
+            })
+        });
+        match found {
+            None => discrepancies.push(Discrepancy::Missing(artifact.clone())),
+            Some((actual_artifact, digest)) => {
+                matched.insert(actual_artifact.clone());
+                if digest != expected_digest {
+                    discrepancies.push(Discrepancy::Mismatched {
+                        artifact: actual_artifact.clone(),
+                        expected: expected_digest.clone(),
+                        actual: digest.clone(),
+                    })
+                }
+            }
+        }
+    }
+    for digest in &expected.bare {
+        if !actual.values().any(|d| d == digest) {
+            discrepancies.push(Discrepancy::Missing(format!("artifact with {}", digest)));
+        }
+    }
+    let expected_kinds = expected
+        .named
+        .keys()
+        .map(|artifact| file_kind(artifact))
+        .collect::<BTreeSet<_>>();
+    for (artifact, digest) in actual {
+        let expected_kind =
+            file_kind(artifact) == WASM || expected_kinds.contains(file_kind(artifact));
+        if expected_kind && !matched.contains(artifact) && !expected.bare.contains(digest) {
+            discrepancies.push(Discrepancy::Extra(artifact.clone()));
+        }
+    }
+
+    discrepancies
+}
+
+/// Returns the artifact filename without the arch suffix, e.g. `cw20_base.wasm` for `cw20_base-aarch64.wasm`.
+fn without_arch_suffix(artifact: &str) -> String {
+    for ext in [WASM_GZ, WASM] {
+        if let Some(stem) = artifact.strip_suffix(ext) {
+            let stem = ARCH_SUFFIXES
+                .iter()
+                .find_map(|suffix| stem.strip_suffix(suffix))
+                .unwrap_or(stem);
+            return format!("{}{}", stem, ext);
+        }
+    }
+
+    artifact.to_string()
+}
+
+/// Returns the kind of the file by its extension, i.e. `.wasm`, `.wasm.gz` or `.json` for schema files.
+fn file_kind(artifact: &str) -> &'static str {
+    [WASM_GZ, WASM]
+        .into_iter()
+        .find(|ext| artifact.ends_with(ext))
+        .unwrap_or(JSON)
+}
+
+/// Returns the SHA-256 digests of the artifacts & schema files in the dir, by artifact name.
+fn artifact_digests(artifacts_dir: &Path) -> Result<BTreeMap<String, String>> {
+    let mut paths = fs::read_dir(artifacts_dir)
+        .context(format!("Failed read from {}", artifacts_dir.display()))?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<Result<Vec<PathBuf>, _>>()?;
+    paths.retain(|path| {
+        let filename = path.rtake(1).display().to_string();
+        path.is_file() && (filename.ends_with(WASM) || filename.ends_with(WASM_GZ))
+    });
+    paths.extend(schema_files(artifacts_dir)?);
+
+    paths
+        .iter()
+        .map(|path| Ok((artifact_name(path), file_sha256_digest(path)?)))
+        .collect()
+}
+
+/// Validates & normalizes a hex encoded SHA-256 digest.
+fn digest_of(digest: &str) -> Result<String> {
+    match digest.len() == 64 && digest.chars().all(|c| c.is_ascii_hexdigit()) {
+        true => Ok(digest.to_ascii_lowercase()),
+        false => Err(anyhow!("Invalid SHA-256 digest: {}", digest)),
+    }
+}
+
+/// Returns the filename, or the path relative to the artifacts dir for schema files.
+fn basename(path: &str) -> String {
+    artifact_name(Path::new(path))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use serde_json::json;
+
+    use crate::verification::{discrepancies, parse_json, parse_lines, Discrepancy};
+
+    const A: &str = "ca978112ca1bbdcafac231b39a23dc4da786eff8147c4e72b9807785afee48bb";
+    const B: &str = "3e23e8160039594a33894f6564e1b1348bbd7a0088d42c4acb73eeaed59c009d";
+
+    #[test]
+    fn parses_sha256sum_and_bare_digests() {
+        let expected = parse_lines(&format!(
+            "{}  a.wasm\n{} *./artifacts/b.wasm\n\n{}\n",
+            A,
+            B,
+            A.to_uppercase()
+        ))
+        .unwrap();
+
+        assert_eq!(Some(A), expected.named.get("a.wasm").map(String::as_str));
+        assert_eq!(Some(B), expected.named.get("b.wasm").map(String::as_str));
+        assert_eq!(vec![A.to_string()], expected.bare);
+        assert!(parse_lines("not-a-digest  a.wasm").is_err());
+    }
+
+    #[test]
+    fn parses_json_manifests() {
+        let build_info = json!({"contracts": [{"artifact": "a.wasm", "sha256": A}]});
+        let checksums = json!({"artifacts": [{"name": "a.wasm", "digests": {"sha256": A}}]});
+        let gzipped = json!({"artifacts": [{
+            "name": "a.wasm",
+            "digests": {"sha256": A},
+            "gzip": {"name": "a.wasm.gz", "digests": {"sha256": B}},
+        }]});
+        let map = json!({ "a.wasm": A });
+        let list = json!([A]);
+
+        assert_eq!(parse_json(&build_info).unwrap(), parse_json(&map).unwrap());
+        assert_eq!(parse_json(&checksums).unwrap(), parse_json(&map).unwrap());
+        assert_eq!(
+            Some(B),
+            parse_json(&gzipped)
+                .unwrap()
+                .named
+                .get("a.wasm.gz")
+                .map(String::as_str)
+        );
+        assert_eq!(vec![A.to_string()], parse_json(&list).unwrap().bare);
+    }
+
+    #[test]
+    fn matches_artifacts_without_arch_suffix() {
+        // rust-optimizer's checksums.txt
+        let expected =
+            parse_lines(&format!("{}  cw20_base.wasm\n{}  cw4_group.wasm\n", A, B)).unwrap();
+        let actual = BTreeMap::from([
+            ("cw20_base-x86_64.wasm".to_string(), A.to_string()),
+            ("cw4_group-x86_64.wasm".to_string(), A.to_string()),
+        ]);
+
+        assert_eq!(
+            vec![Discrepancy::Mismatched {
+                artifact: "cw4_group-x86_64.wasm".to_string(),
+                expected: B.to_string(),
+                actual: A.to_string(),
+            }],
+            discrepancies(&expected, &actual)
+        );
+    }
+
+    #[test]
+    fn ignores_kinds_of_artifacts_which_arent_expected() {
+        // rust-optimizer's checksums.txt, next to a build with --gzip & --schema
+        let expected = parse_lines(&format!("{}  cw20_base.wasm\n", A)).unwrap();
+        let actual = BTreeMap::from([
+            ("cw20_base-x86_64.wasm".to_string(), A.to_string()),
+            ("cw20_base-x86_64.wasm.gz".to_string(), B.to_string()),
+            ("schema/cw20-base/cw20-base.json".to_string(), B.to_string()),
+        ]);
+        assert!(discrepancies(&expected, &actual).is_empty());
+
+        // on-chain code hashes
+        let expected = parse_lines(&format!("{}\n", A)).unwrap();
+        assert!(discrepancies(&expected, &actual).is_empty());
+
+        // a checksums.json listing the gzipped artifacts
+        let expected = parse_json(&json!({"artifacts": [{
+            "name": "cw20_base-x86_64.wasm",
+            "digests": {"sha256": A},
+            "gzip": {"name": "cw20_base-x86_64.wasm.gz", "digests": {"sha256": B}},
+        }]}))
+        .unwrap();
+        let mut actual = actual;
+        actual.insert("cw4_group-x86_64.wasm.gz".to_string(), A.to_string());
+        assert_eq!(
+            vec![Discrepancy::Extra("cw4_group-x86_64.wasm.gz".to_string())],
+            discrepancies(&expected, &actual)
+        );
+    }
+
+    #[test]
+    fn reports_missing_extra_and_mismatched_artifacts() {
+        let expected = parse_lines(&format!("{}  a.wasm\n{}  b.wasm\n", A, B)).unwrap();
+        let actual = BTreeMap::from([
+            ("a.wasm".to_string(), B.to_string()),
+            ("c.wasm".to_string(), A.to_string()),
+        ]);
+
+        assert_eq!(
+            vec![
+                Discrepancy::Mismatched {
+                    artifact: "a.wasm".to_string(),
+                    expected: A.to_string(),
+                    actual: B.to_string(),
+                },
+                Discrepancy::Missing("b.wasm".to_string()),
+                Discrepancy::Extra("c.wasm".to_string()),
+            ],
+            discrepancies(&expected, &actual)
+        );
+    }
+}
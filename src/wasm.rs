@@ -0,0 +1,272 @@
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use wasmparser::{ExternalKind, Name, NameSectionReader, Parser, Payload, TypeRef};
+
+use crate::hashing::sha256_digest;
+
+const NAME_SECTION: &str = "name";
+
+/// A section or function of a WASM module.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Item {
+    pub name: String,
+    pub size: usize,
+    /// SHA-256 digest of its contents.
+    pub digest: String,
+}
+
+/// The sections & function bodies of a WASM module.
+#[derive(Debug, Clone, Default)]
+pub struct ModuleItems {
+    pub sections: Vec<Item>,
+    /// Functions are named by their export, or by their index if not exported.
+    pub functions: Vec<Item>,
+}
+
+/// Parses the WASM module into its sections & function bodies.
+pub fn module_items(wasm: &[u8]) -> Result<ModuleItems> {
+    let mut items = ModuleItems::default();
+    let mut imported_functions = 0;
+    let mut exported_functions = BTreeMap::new();
+
+    for payload in Parser::new(0).parse_all(wasm) {
+        let payload = payload?;
+        if let Some((id, range)) = payload.as_section() {
+            let name = match &payload {
+                Payload::CustomSection(section) => format!("custom \"{}\"", section.name()),
+                _ => section_name(id).to_string(),
+            };
+            items.sections.push(item(name, &wasm[range])?);
+        }
+
+        match payload {
+            Payload::ImportSection(imports) => {
+                for import in imports {
+                    if let TypeRef::Func(_) = import?.ty {
+                        imported_functions += 1;
+                    }
+                }
+            }
+            Payload::ExportSection(exports) => {
+                for export in exports {
+                    let export = export?;
+                    if export.kind == ExternalKind::Func {
+                        exported_functions.insert(export.index, export.name.to_string());
+                    }
+                }
+            }
+            Payload::CodeSectionEntry(body) => {
+                // function indices start after the imported functions
+                let index = imported_functions + items.functions.len() as u32;
+                let name = exported_functions
+                    .get(&index)
+                    .cloned()
+                    .unwrap_or_else(|| format!("func[{}]", index));
+                items.functions.push(item(name, &wasm[body.range()])?);
+            }
+            _ => {}
+        }
+    }
+
+    Ok(items)
+}
+
+/// Returns the names & sizes of the function bodies of the WASM module.
+/// Functions are named by the names section, or by their index if not named.
+pub fn function_sizes(wasm: &[u8]) -> Result<Vec<(String, usize)>> {
+    let mut imported_functions = 0;
+    let mut names = BTreeMap::new();
+    let mut sizes = vec![];
+
+    for payload in Parser::new(0).parse_all(wasm) {
+        match payload? {
+            Payload::ImportSection(imports) => {
+                for import in imports {
+                    if let TypeRef::Func(_) = import?.ty {
+                        imported_functions += 1;
+                    }
+                }
+            }
+            Payload::CodeSectionEntry(body) => sizes.push(body.range().len()),
+            Payload::CustomSection(section) if section.name() == NAME_SECTION => {
+                for name in NameSectionReader::new(section.data(), section.data_offset()) {
+                    if let Name::Function(functions) = name? {
+                        for function in functions {
+                            let function = function?;
+                            names.insert(function.index, function.name.to_string());
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(sizes
+        .into_iter()
+        .enumerate()
+        .map(|(i, size)| {
+            // function indices start after the imported functions
+            let index = imported_functions + i as u32;
+            let name = names
+                .remove(&index)
+                .unwrap_or_else(|| format!("func[{}]", index));
+            (name, size)
+        })
+        .collect())
+}
+
+/// Returns the contents of all data segments of the WASM module.
+pub fn data(wasm: &[u8]) -> Result<Vec<u8>> {
+    let mut data = vec![];
+    for payload in Parser::new(0).parse_all(wasm) {
+        if let Payload::DataSection(segments) = payload? {
+            for segment in segments {
+                data.extend_from_slice(segment?.data);
+            }
+        }
+    }
+
+    Ok(data)
+}
+
+/// Returns whether the bytes contain the given byte sequence.
+pub fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    !needle.is_empty()
+        && haystack
+            .windows(needle.len())
+            .any(|window| window == needle)
+}
+
+/// Returns a description of each item which differs between the two lists, or only exists in one of them.
+pub fn diff(a: &[Item], b: &[Item]) -> Vec<String> {
+    let b_items = b
+        .iter()
+        .map(|item| (&item.name, item))
+        .collect::<BTreeMap<_, _>>();
+    let mut diffs = a
+        .iter()
+        .filter_map(|a_item| match b_items.get(&a_item.name) {
+            Some(b_item) if b_item.digest == a_item.digest => None,
+            Some(b_item) => Some(format!(
+                "{} ({} vs {} bytes)",
+                a_item.name, a_item.size, b_item.size
+            )),
+            None => Some(format!("{} (only in the first)", a_item.name)),
+        })
+        .collect::<Vec<_>>();
+    diffs.extend(
+        b.iter()
+            .filter(|b_item| !a.iter().any(|a_item| a_item.name == b_item.name))
+            .map(|b_item| format!("{} (only in the second)", b_item.name)),
+    );
+
+    diffs
+}
+
+fn item(name: String, contents: &[u8]) -> Result<Item> {
+    Ok(Item {
+        name,
+        size: contents.len(),
+        digest: sha256_digest(contents)?,
+    })
+}
+
+fn section_name(id: u8) -> &'static str {
+    match id {
+        0 => "custom",
+        1 => "type",
+        2 => "import",
+        3 => "function",
+        4 => "table",
+        5 => "memory",
+        6 => "global",
+        7 => "export",
+        8 => "start",
+        9 => "element",
+        10 => "code",
+        11 => "data",
+        12 => "data count",
+        13 => "tag",
+        _ => "unknown",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::wasm::{contains, data, diff, function_sizes, module_items};
+
+    const HEADER: [u8; 8] = [0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00];
+    // (type (func)) (func (export "a")) (func)
+    const SECTIONS: [u8; 19] = [
+        0x01, 0x04, 0x01, 0x60, 0x00, 0x00, // type
+        0x03, 0x03, 0x02, 0x00, 0x00, // function
+        0x07, 0x05, 0x01, 0x01, 0x61, 0x00, 0x00, // export
+        0x0a, // code
+    ];
+
+    fn module(code: &[u8]) -> Vec<u8> {
+        [&HEADER[..], &SECTIONS[..], &[code.len() as u8], code].concat()
+    }
+
+    #[test]
+    fn diffs_sections_and_functions() {
+        let a = module_items(&module(&[0x02, 0x02, 0x00, 0x0b, 0x02, 0x00, 0x0b])).unwrap();
+        let b = module_items(&module(&[0x02, 0x03, 0x00, 0x01, 0x0b, 0x02, 0x00, 0x0b])).unwrap();
+
+        assert_eq!(
+            vec!["type", "function", "export", "code"],
+            a.sections
+                .iter()
+                .map(|s| s.name.as_str())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec!["a", "func[1]"],
+            a.functions
+                .iter()
+                .map(|f| f.name.as_str())
+                .collect::<Vec<_>>()
+        );
+        assert!(diff(&a.sections, &a.sections).is_empty());
+        assert_eq!(vec!["code (7 vs 8 bytes)"], diff(&a.sections, &b.sections));
+        assert_eq!(vec!["a (2 vs 3 bytes)"], diff(&a.functions, &b.functions));
+    }
+
+    #[test]
+    fn names_functions_by_the_names_section() {
+        // (@name "f") for function 0
+        let names = [
+            0x00, 0x0b, 0x04, b'n', b'a', b'm', b'e', 0x01, 0x04, 0x01, 0x00, 0x01, b'f',
+        ];
+        let wasm = [
+            module(&[0x02, 0x02, 0x00, 0x0b, 0x03, 0x00, 0x01, 0x0b]),
+            names.to_vec(),
+        ]
+        .concat();
+
+        assert_eq!(
+            vec![("f".to_string(), 2), ("func[1]".to_string(), 3)],
+            function_sizes(&wasm).unwrap()
+        );
+    }
+
+    #[test]
+    fn finds_paths_in_data_segments() {
+        let path = b"/home/user/src/lib.rs";
+        // (memory 1) (data (i32.const 0) "/home/user/src/lib.rs")
+        let wasm = [
+            &HEADER[..],
+            &[0x05, 0x03, 0x01, 0x00, 0x01],
+            &[0x0b, path.len() as u8 + 6, 0x01, 0x00, 0x41, 0x00, 0x0b],
+            &[path.len() as u8],
+            &path[..],
+        ]
+        .concat();
+
+        assert!(contains(&data(&wasm).unwrap(), b"/home/user"));
+        assert!(!contains(&data(&wasm).unwrap(), b"/build/user"));
+        assert!(!contains(&data(&wasm).unwrap(), b""));
+    }
+}
@@ -0,0 +1,145 @@
+use std::{
+    collections::BTreeMap,
+    ffi::OsStr,
+    path::{Path, PathBuf},
+    sync::mpsc,
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use cargo::{core::Workspace, Config};
+use notify_debouncer_mini::{
+    new_debouncer,
+    notify::{RecursiveMode, Watcher},
+    Debouncer,
+};
+
+use crate::{
+    affected::{affected_contracts, package_roots},
+    build, contracts, create_artifacts_dir,
+    optimization::Outcome,
+    Options,
+};
+
+const DEBOUNCE: Duration = Duration::from_millis(500);
+const CARGO_TOML: &str = "Cargo.toml";
+const SRC: &str = "src";
+
+/// Watches the workspace contracts, and rebuilds & reoptimizes the ones affected by any source changes.
+/// The cargo config & workspace are kept loaded in between the iterations.
+pub fn watch(cfg: &Config, manifest_path: &Path, options: &Options) -> Result<()> {
+    let mut ws = Workspace::new(manifest_path, cfg)?;
+    let output_dir = create_artifacts_dir(&ws)?;
+
+    let (tx, rx) = mpsc::channel();
+    let mut debouncer = new_debouncer(DEBOUNCE, tx)?;
+    let mut watched = watch_paths(&mut debouncer, &ws, &BTreeMap::new())?;
+
+    // start off with a full build, and only rebuild the affected contracts after that
+    let mut pending = contracts(&ws);
+    let mut partial = false;
+    loop {
+        if !pending.is_empty() {
+            let started = Instant::now();
+            match build(cfg, &ws, &pending, &output_dir, partial, options) {
+                Ok(optimized_wasms) => {
+                    let count = |outcome| {
+                        optimized_wasms
+                            .iter()
+                            .filter(|wasm| wasm.outcome == outcome)
+                            .count()
+                    };
+                    println!(
+                        "🔁  Rebuilt {} contract(s) in {:.1}s: {} optimized, {} restored, {} unchanged",
+                        pending.len(),
+                        started.elapsed().as_secs_f32(),
+                        count(Outcome::Optimized),
+                        count(Outcome::Restored),
+                        count(Outcome::Skipped),
+                    );
+                }
+                Err(e) => println!("❌  Build failed: {:#}", e),
+            }
+            partial = true;
+            println!("👀  Watching for changes...");
+        }
+
+        let changed = match rx.recv()? {
+            Ok(events) => events
+                .into_iter()
+                .map(|event| event.path)
+                .collect::<Vec<_>>(),
+            Err(e) => {
+                println!("    ...⚠️  watch error: {}", e);
+                continue;
+            }
+        };
+
+        // manifest changes may change the workspace members & dependencies
+        if changed
+            .iter()
+            .any(|path| path.file_name() == Some(OsStr::new(CARGO_TOML)))
+        {
+            match Workspace::new(manifest_path, cfg) {
+                Ok(reloaded) => {
+                    ws = reloaded;
+                    watched = watch_paths(&mut debouncer, &ws, &watched)?;
+                }
+                Err(e) => {
+                    println!("❌  Couldn't reload workspace: {:#}", e);
+                    pending = vec![];
+                    continue;
+                }
+            }
+        }
+
+        pending = affected_contracts(&ws, &changed);
+        if !pending.is_empty() {
+            println!(
+                "🔄  Changed: {}",
+                pending
+                    .iter()
+                    .map(|p| p.name().to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        }
+    }
+}
+
+/// Watches the sources of all contracts & the `Cargo.lock`, and returns the watched paths.
+/// Any previously watched paths which are no longer relevant are unwatched.
+///
+/// Package root dirs are watched non-recursively, so that manifests replaced by editors are still picked up,
+/// but their `target` dirs are not.
+fn watch_paths<T: Watcher>(
+    debouncer: &mut Debouncer<T>,
+    ws: &Workspace,
+    watched: &BTreeMap<PathBuf, RecursiveMode>,
+) -> Result<BTreeMap<PathBuf, RecursiveMode>> {
+    let mut paths = contracts(ws)
+        .iter()
+        .flat_map(|contract| package_roots(ws, contract))
+        .flat_map(|root| {
+            [
+                (root.join(SRC), RecursiveMode::Recursive),
+                (root, RecursiveMode::NonRecursive),
+            ]
+        })
+        .collect::<BTreeMap<_, _>>();
+    // for the Cargo.lock
+    paths.insert(ws.root().to_path_buf(), RecursiveMode::NonRecursive);
+    paths.retain(|path, _| path.exists());
+
+    for path in watched.keys().filter(|path| !paths.contains_key(*path)) {
+        let _ = debouncer.watcher().unwatch(path);
+    }
+    for (path, mode) in paths
+        .iter()
+        .filter(|(path, _)| !watched.contains_key(*path))
+    {
+        debouncer.watcher().watch(path, *mode)?;
+    }
+
+    Ok(paths)
+}
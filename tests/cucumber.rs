@@ -67,12 +67,13 @@ async fn is_in_workspace(world: &mut CwWorld, ws: String) -> anyhow::Result<()>
 }
 
 #[when(
-    regex = r"the user\s?(successfully|unsuccessfully)? runs cw-optimizoor\s?(for the first time|again)?"
+    regex = r#"the user\s?(successfully|unsuccessfully)? runs cw-optimizoor\s?(for the first time|again)?(?: with "(.*)")?"#
 )]
 async fn runs_cw_optimizoor(
     world: &mut CwWorld,
     result: String,
     cond: String,
+    args: String,
 ) -> anyhow::Result<()> {
     if !cond.is_empty() && cond.ne("again") {
         let artifacts = world.ws_root.join("artifacts");
@@ -89,6 +90,7 @@ async fn runs_cw_optimizoor(
     let mut cmd = AssertCommand::cargo_bin(CARGO_CW_OPTIMIZOOR)?;
     cmd.current_dir(world.ws_root.as_path());
     cmd.arg(CW_OPTIMIZOOR);
+    cmd.args(args.split_whitespace());
 
     let assert = match result.as_str() {
         "successfully" => cmd.assert().success(),
@@ -229,6 +231,27 @@ async fn str_is_reoptimized(world: &mut CwWorld, name: String) -> anyhow::Result
     Ok(())
 }
 
+#[then(expr = "the artifacts dir contains {string}")]
+async fn artifacts_dir_contains_str(world: &mut CwWorld, filename: String) -> anyhow::Result<()> {
+    let path = world.ws_root.join("artifacts").join(&filename);
+    assert!(path.is_file(), "missing {}", path.display());
+
+    Ok(())
+}
+
+#[then(expr = "the output contains {string}")]
+async fn output_contains_str(world: &mut CwWorld, text: String) -> anyhow::Result<()> {
+    world
+        .cmd_output
+        .as_ref()
+        .expect("missing cmd output")
+        .clone()
+        .assert()
+        .stdout(predicate::str::contains(text));
+
+    Ok(())
+}
+
 #[tokio::main]
 async fn main() {
     CwWorld::cucumber()
@@ -6,6 +6,7 @@ Feature: Optimizing a CosmWasm workspace
     Then 8 contracts are optimized
     And 8 wasm files exist in the artifacts dir
     And each artifact contains a function named "execute"
+    And the artifacts dir contains "build-info.json"
 
   Scenario: Reoptimizes workspace a second time
     Given the user is in the workspace "cw-plus"
@@ -31,3 +32,18 @@ Feature: Optimizing a CosmWasm workspace
     But "cw1_subkeys" is reoptimized
     And 8 wasm files exist in the artifacts dir
     And each artifact contains a function named "execute"
+
+  Scenario: Compiles only contracts affected since a git ref
+    Given the user is in the workspace "cw-plus"
+    And the user makes a change in the "cw1-subkeys" contract
+    When the user successfully runs cw-optimizoor with "--changed-since HEAD"
+    Then 1 contracts are optimized
+    But "cw1_subkeys" is reoptimized
+    And 8 wasm files exist in the artifacts dir
+
+  Scenario: Verifies the artifacts against the provenance attestation
+    Given the user is in the workspace "cw-plus"
+    When the user successfully runs cw-optimizoor for the first time
+    And the user successfully runs cw-optimizoor with "verify-attestation artifacts/provenance.intoto.json"
+    Then the output contains "All artifacts match the attestation"
+    And 0 contracts are optimized
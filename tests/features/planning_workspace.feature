@@ -0,0 +1,19 @@
+Feature: Planning a CosmWasm workspace
+
+  Scenario: Shows the build plan without building anything
+    Given the user is in the workspace "cw-plus"
+    When the user successfully runs cw-optimizoor with "plan"
+    Then the output contains "cw1-whitelist"
+    And the output contains "individual (depends on cw1-whitelist with the `library` feature)"
+    And the output contains "Nothing was built"
+    But 0 contracts are optimized
+
+  Scenario: Shows which contracts would be reoptimized
+    Given the user is in the workspace "cw-plus"
+    When the user successfully runs cw-optimizoor for the first time
+    And the user makes a change in the "cw1-subkeys" contract
+    And the user successfully runs cw-optimizoor with "plan"
+    Then the output contains "group:        common"
+    And the output contains "cache:        likely hit"
+    And the output contains "cache:        miss (sources modified since the last build)"
+    But 0 contracts are optimized